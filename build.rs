@@ -0,0 +1,8 @@
+fn main() {
+    // Expose the target triple we are compiled for so `local_target` can use it
+    // on platforms where we don't construct the triple ourselves.
+    println!(
+        "cargo:rustc-env=TARGET={}",
+        std::env::var("TARGET").expect("cargo always sets TARGET for build scripts")
+    );
+}
@@ -0,0 +1,43 @@
+//! Extracting the archive formats TVM sources and releases are distributed in.
+use std::{fs::File, path::Path};
+
+use super::core::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    TarGz,
+    TarXz,
+    Zip,
+}
+
+impl Format {
+    /// The format of an archive, going by its file name.
+    pub(crate) fn from_path(path: &Path) -> Option<Format> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Format::TarGz)
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            Some(Format::TarXz)
+        } else if name.ends_with(".zip") {
+            Some(Format::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Extract the archive at `path` into `destination`.
+pub(crate) fn extract(path: &Path, format: Format, destination: &Path) -> Result<(), Error> {
+    let file = File::open(path)?;
+    match format {
+        Format::TarGz => tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(destination)?,
+        Format::TarXz => tar::Archive::new(xz2::read::XzDecoder::new(file)).unpack(destination)?,
+        Format::Zip => zip::ZipArchive::new(file)
+            .and_then(|mut archive| archive.extract(destination))
+            .map_err(|err| Error::InvalidArchive {
+                archive: path.to_path_buf(),
+                message: err.to_string(),
+            })?,
+    }
+    Ok(())
+}
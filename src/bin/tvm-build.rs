@@ -1,25 +1,265 @@
+use std::{
+    path::PathBuf,
+    process::Command,
+    str::FromStr,
+    sync::mpsc::{self, Receiver},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
 use structopt::StructOpt;
-use tracing_subscriber;
-use tvm_build::{self, build, BuildConfig, UserSettings};
+use tvm_build::{
+    self, build, BuildConfig, BuildEvent, BuildLayout, Preset, Profile, Revision,
+    RevisionSpec, Runtime, UserSettings,
+};
 
 #[derive(StructOpt, Debug)]
 #[structopt()]
 struct InstallCommand {
-    revision: String,
+    /// The branch, tag or commit to build, optionally preceded by a GitHub repository as in
+    /// `apache/tvm@v0.10.0`. Revisions starting with `-` are rejected, as they are by git,
+    /// name such a branch or tag in full as `refs/heads/<branch>` or `refs/tags/<tag>`, or
+    /// give its full commit SHA.
+    revision: RevisionSpec,
     repository: Option<String>,
     #[structopt(short, long)]
     /// The directory to build TVM in.
     output_path: Option<String>,
+    /// Build this existing, possibly read-only, source tree instead of cloning. It is
+    /// never written to.
+    #[structopt(long)]
+    repository_path: Option<String>,
+    /// Build in debug mode, by default TVM is built in release mode.
     #[structopt(short, long)]
     debug: bool,
+    /// The CMake build type: Debug, Release, RelWithDebInfo or MinSizeRel.
+    #[structopt(long, conflicts_with = "debug")]
+    profile: Option<Profile>,
     #[structopt(short, long)]
     clean: bool,
+    /// Rebuild even if nothing changed since the last build.
+    #[structopt(long)]
+    force: bool,
+    /// The target triple to cross compile for, e.g. aarch64-unknown-linux-gnu or aarch64-apple-ios.
+    #[structopt(long)]
+    target: Option<String>,
+    /// Where to place the build directory: next to the source (parallel) or inside it (in-source).
+    #[structopt(long, default_value = "parallel")]
+    build_layout: BuildLayout,
+    /// Log more output, `-v` for debug output and `-vv` to also print the commands the
+    /// build runs.
+    #[structopt(short, long, parse(from_occurrences))]
+    verbose: u8,
+    /// Keep compiling unrelated targets after a compile error.
+    #[structopt(long)]
+    keep_going: bool,
+    /// Build the source in this .tar.gz, .tar.xz or .zip archive instead of cloning.
+    #[structopt(long, parse(from_os_str))]
+    source_archive: Option<PathBuf>,
+    /// Only clone this many commits of history.
+    #[structopt(long)]
+    clone_depth: Option<u32>,
+    /// Only clone the history since this date, e.g. 2021-06-01.
+    #[structopt(long)]
+    shallow_since: Option<String>,
+    /// Only clone the history since this tag.
+    #[structopt(long)]
+    shallow_since_tag: Option<String>,
+    /// Fall back to the full history when the shallow clone lacks the commit to check out.
+    #[structopt(long)]
+    fallback_to_full: bool,
+    /// Check out the exact commits recorded by `tvm-build lock` or the previous build.
+    #[structopt(long)]
+    locked: bool,
+    /// Build and run TVM's C++ unit tests, requires --use-gtest.
+    #[structopt(long)]
+    run_cpp_tests: bool,
+    /// Update this submodule to the tip of its remote branch instead of the pinned commit,
+    /// may be repeated.
+    #[structopt(long = "track-remote", number_of_values = 1)]
+    track_remote_submodules: Vec<String>,
+    /// Build the C++ API documentation with doxygen.
+    #[structopt(long)]
+    build_docs: bool,
+    /// Write the build's dependency graph as a Graphviz dot file.
+    #[structopt(long)]
+    dependency_graph: bool,
+    /// Report the number of compiler warnings emitted by the build.
+    #[structopt(long)]
+    collect_warnings: bool,
+    /// Load the built TVM runtime to check the build is usable.
+    #[structopt(long)]
+    smoke_test: bool,
+    /// Install the TVM Python package after building.
+    #[structopt(long)]
+    with_python: bool,
+    /// The Python interpreter to install the TVM Python package with.
+    #[structopt(long, default_value = "python3", parse(from_os_str))]
+    python: PathBuf,
+    /// Install a prebuilt release from this base URL when one matches, instead of building.
+    #[structopt(long)]
+    prebuilt_url: Option<String>,
+    /// A preset to base the settings on: embedded-runtime.
+    #[structopt(long)]
+    preset: Option<Preset>,
+    /// The runtime to build: graph, aot or vm.
+    #[structopt(long)]
+    runtime: Option<Runtime>,
+    /// Build only the runtime library rather than the whole compiler.
+    #[structopt(long, conflicts_with = "with-python")]
+    runtime_only: bool,
+    /// The C++ standard to build with, e.g. 17.
+    #[structopt(long)]
+    cxx_standard: Option<u32>,
+    /// Link third party dependencies statically where supported.
+    #[structopt(long)]
+    prefer_static: bool,
+    /// Strip the symbols not needed for dynamic linking from the installed libraries.
+    #[structopt(long)]
+    strip: bool,
+    /// Make the installed libraries relocatable by resolving dependencies relative to themselves.
+    #[structopt(long)]
+    relocatable: bool,
+    /// The number of compile jobs to run in parallel, by default one per logical CPU.
+    #[structopt(short, long)]
+    jobs: Option<usize>,
+    /// Only run as many compile jobs as fit in the available memory, assuming about 2GB each.
+    #[structopt(long)]
+    limit_jobs_by_memory: bool,
+    /// The maximum number of parallel link steps, only supported by Ninja.
+    #[structopt(long)]
+    link_jobs: Option<usize>,
+    /// Stop CMake's configure step after this many seconds.
+    #[structopt(long)]
+    configure_timeout: Option<u64>,
+    /// The CMake generator, e.g. Ninja or "Unix Makefiles", by default Ninja when installed.
+    #[structopt(long)]
+    generator: Option<String>,
+    /// The toolset for Visual Studio generators, e.g. v142.
+    #[structopt(long)]
+    toolset: Option<String>,
+    /// The platform for Visual Studio generators, e.g. x64.
+    #[structopt(long)]
+    platform: Option<String>,
+    /// Fetch the branch and update an existing checkout to its tip before building.
+    #[structopt(long, conflicts_with = "offline")]
+    update: bool,
+    /// Don't check whether an existing checkout is behind the remote.
+    #[structopt(long)]
+    offline: bool,
+    /// The proxy to use when fetching the repository.
+    #[structopt(long)]
+    proxy: Option<String>,
+    /// The private SSH key to fetch over SSH with, by default the SSH agent's keys are used.
+    #[structopt(long, parse(from_os_str))]
+    ssh_key: Option<PathBuf>,
+    /// The SHA-256 digest the prebuilt release must match.
+    #[structopt(long)]
+    prebuilt_sha256: Option<String>,
+    /// Write a sourceable env.sh (and env.bat on Windows) into the revision directory.
+    #[structopt(long)]
+    write_env: bool,
+    /// A shell command to run after a successful build, with TVM_HOME, TVM_LIBRARY_PATH and
+    /// TVM_INCLUDE_DIR pointing at the build.
+    #[structopt(long)]
+    post_build: Option<String>,
+    /// An existing TVM config.cmake to build with, settings passed as flags take precedence.
+    #[structopt(long, parse(from_os_str))]
+    config_cmake: Option<PathBuf>,
+    /// Enable every backend this machine supports, settings passed as flags take precedence.
+    #[structopt(long)]
+    auto: bool,
+    /// Set a CMake option, e.g. `-D USE_CUTLASS=ON`, including ones without a flag.
+    #[structopt(short = "D", parse(try_from_str = parse_define), number_of_values = 1)]
+    defines: Vec<(String, String)>,
+    /// An option, e.g. USE_LLVM, whose value isn't checked to be an existing path.
+    #[structopt(long = "unchecked-path", number_of_values = 1)]
+    unchecked_paths: Vec<String>,
+    #[structopt(flatten)]
+    settings: UserSettings,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+struct LockCommand {
+    /// The branch, tag or commit to lock, optionally as `owner/name@revision`.
+    revision: RevisionSpec,
+    repository: Option<String>,
+    #[structopt(short, long)]
+    /// The directory to build TVM in.
+    output_path: Option<String>,
+    /// The target triple to lock the settings for, by default the host.
+    #[structopt(long)]
+    target: Option<String>,
+    /// Set a CMake option, e.g. `-D USE_CUTLASS=ON`, including ones without a flag.
+    #[structopt(short = "D", parse(try_from_str = parse_define), number_of_values = 1)]
+    defines: Vec<(String, String)>,
+    /// An option, e.g. USE_LLVM, whose value isn't checked to be an existing path.
+    #[structopt(long = "unchecked-path", number_of_values = 1)]
+    unchecked_paths: Vec<String>,
+    #[structopt(flatten)]
+    settings: UserSettings,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+struct CheckCommand {
+    /// The branch, tag or commit to check the settings against, optionally as
+    /// `owner/name@revision`.
+    revision: RevisionSpec,
+    repository: Option<String>,
     #[structopt(short, long)]
-    verbose: bool,
+    /// The directory to build TVM in.
+    output_path: Option<String>,
+    /// The target triple to check the settings for, by default the host.
+    #[structopt(long)]
+    target: Option<String>,
+    /// Set a CMake option, e.g. `-D USE_CUTLASS=ON`, including ones without a flag.
+    #[structopt(short = "D", parse(try_from_str = parse_define), number_of_values = 1)]
+    defines: Vec<(String, String)>,
+    /// An option, e.g. USE_LLVM, whose value isn't checked to be an existing path.
+    #[structopt(long = "unchecked-path", number_of_values = 1)]
+    unchecked_paths: Vec<String>,
     #[structopt(flatten)]
     settings: UserSettings,
 }
 
+#[derive(StructOpt, Debug)]
+#[structopt()]
+struct GcCommand {
+    revision: String,
+    #[structopt(short, long)]
+    /// The directory that TVM was built in.
+    output_path: Option<String>,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+struct ListCommand {
+    #[structopt(short, long)]
+    /// The directory that TVM was built in.
+    output_path: Option<String>,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+struct EnvCommand {
+    revision: String,
+    #[structopt(short, long)]
+    /// The directory that TVM was built in.
+    output_path: Option<String>,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+struct DiffCommand {
+    revision_a: String,
+    revision_b: String,
+    #[structopt(short, long)]
+    /// The directory that TVM was built in.
+    output_path: Option<String>,
+}
+
 #[derive(StructOpt, Debug)]
 #[structopt()]
 struct UninstallCommand {
@@ -33,33 +273,340 @@ struct UninstallCommand {
 #[structopt()]
 struct VersionCommand {
     revision: String,
+    /// The path of the TVM Python package within the source tree, by default the known
+    /// layouts are searched.
+    #[structopt(long)]
+    python_subpath: Option<String>,
+}
+
+#[derive(Debug)]
+enum ConfigFormat {
+    CMake,
+}
+
+impl FromStr for ConfigFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cmake" => Ok(ConfigFormat::CMake),
+            _ => Err(format!("unknown config format `{}`, expected one of: cmake", s)),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+struct ConfigCommand {
+    /// The format to print the configuration in.
+    #[structopt(long, default_value = "cmake")]
+    format: ConfigFormat,
+    /// Set a CMake option, e.g. `-D USE_CUTLASS=ON`, including ones without a flag.
+    #[structopt(short = "D", parse(try_from_str = parse_define), number_of_values = 1)]
+    defines: Vec<(String, String)>,
+    /// An option, e.g. USE_LLVM, whose value isn't checked to be an existing path.
+    #[structopt(long = "unchecked-path", number_of_values = 1)]
+    unchecked_paths: Vec<String>,
+    #[structopt(flatten)]
+    settings: UserSettings,
 }
 
 #[derive(StructOpt, Debug)]
 #[structopt(about = "A CLI for maintaining TVM installations.")]
+#[allow(clippy::large_enum_variant)]
 enum TVMBuildArgs {
     /// Install a revision of TVM on your machine.
     Install(InstallCommand),
+    /// Record the exact commits and settings of a revision without building it.
+    Lock(LockCommand),
+    /// Check a set of settings by running only CMake's configure step.
+    Check(CheckCommand),
+    /// List the revisions of TVM on your machine, whether they are built and their size.
+    List(ListCommand),
     /// Remove a revision of TVM on your machine.
     Uninstall(UninstallCommand),
+    /// Remove stale build artifacts of a revision without a full clean.
+    Gc(GcCommand),
+    /// Print the environment for using a revision, e.g. `eval "$(tvm-build env main)"`.
+    Env(EnvCommand),
+    /// Print the settings which differ between the builds of two revisions.
+    Diff(DiffCommand),
     /// Get the configuration of the version.
     VersionConfig(VersionCommand),
+    /// Print the configuration corresponding to a set of build settings.
+    Config(ConfigCommand),
+}
+
+/// A command running `command` with the platform's shell.
+fn shell_command(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut cmd = Command::new(shell);
+    cmd.arg(flag).arg(command);
+    cmd
+}
+
+/// Print a line per submodule as it is updated, and its fetch progress, until the
+/// build drops its end of the channel.
+fn print_submodule_progress(events: Receiver<BuildEvent>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for event in events {
+            match event {
+                BuildEvent::UpdatingSubmodule { name, index, total } => {
+                    println!("[{}/{}] updating submodule {}", index, total, name);
+                }
+                BuildEvent::FetchingSubmodule {
+                    name,
+                    received_objects,
+                    total_objects,
+                } => {
+                    println!(
+                        "    {}: received {}/{} objects",
+                        name, received_objects, total_objects
+                    );
+                }
+                _ => {}
+            }
+        }
+    })
+}
+
+/// A size in bytes in the largest unit it is at least one of, e.g. `1.5 GiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Parse a `KEY=VALUE` CMake define.
+fn parse_define(define: &str) -> Result<(String, String), String> {
+    match define.split_once('=') {
+        Some((key, _)) if key.trim().is_empty() => {
+            Err(format!("expected a key before `=`, got `{}`", define))
+        }
+        Some((key, value)) => Ok((key.trim().to_string(), value.to_string())),
+        None => Err(format!("expected KEY=VALUE, got `{}`", define)),
+    }
+}
+
+/// Apply the `-D` defines to `config`, see `BuildConfig::set_option`.
+fn with_defines(
+    mut config: BuildConfig,
+    defines: &[(String, String)],
+) -> anyhow::Result<BuildConfig> {
+    for (key, value) in defines {
+        config.set_option(key, value)?;
+    }
+    Ok(config)
 }
 
 fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
     let args = TVMBuildArgs::from_args();
+    // A single subscriber for every command, the library installs none of its own as
+    // `log_level` is left unset.
+    let log_level = match &args {
+        TVMBuildArgs::Install(install_cmd) => match install_cmd.verbose {
+            0 => tracing::Level::INFO,
+            1 => tracing::Level::DEBUG,
+            _ => tracing::Level::TRACE,
+        },
+        _ => tracing::Level::INFO,
+    };
+    tracing_subscriber::fmt().with_max_level(log_level).init();
     match args {
         TVMBuildArgs::Install(install_cmd) => {
-            let mut config = BuildConfig::default();
-            config.verbose = true;
-            config.branch = Some(install_cmd.revision);
-            config.clean = install_cmd.clean;
-            config.repository = install_cmd.repository;
-            config.verbose = install_cmd.verbose;
-            config.output_path = install_cmd.output_path;
-            config.settings = install_cmd.settings;
-            build(config)?;
+            let settings = install_cmd.settings.or(UserSettings::from_env()?);
+            let config = BuildConfig {
+                branch: Some(install_cmd.revision.revision),
+                clean: install_cmd.clean,
+                force: install_cmd.force,
+                target: install_cmd.target,
+                build_layout: install_cmd.build_layout,
+                profile: install_cmd.profile.unwrap_or(if install_cmd.debug {
+                    Profile::Debug
+                } else {
+                    Profile::Release
+                }),
+                repository: install_cmd.repository.or(install_cmd.revision.repository),
+                repository_path: install_cmd.repository_path,
+                keep_going: install_cmd.keep_going,
+                collect_warnings: install_cmd.collect_warnings,
+                run_cpp_tests: install_cmd.run_cpp_tests,
+                build_docs: install_cmd.build_docs,
+                dependency_graph: install_cmd.dependency_graph,
+                track_remote_submodules: install_cmd.track_remote_submodules,
+                locked: install_cmd.locked,
+                source_archive: install_cmd.source_archive,
+                clone_depth: install_cmd.clone_depth,
+                shallow_since: install_cmd.shallow_since,
+                shallow_since_tag: install_cmd.shallow_since_tag,
+                fallback_to_full: install_cmd.fallback_to_full,
+                install_python: if install_cmd.with_python {
+                    Some(install_cmd.python)
+                } else {
+                    None
+                },
+                log_level: None,
+                output_path: install_cmd.output_path,
+                config_cmake: install_cmd.config_cmake,
+                prebuilt_url: install_cmd.prebuilt_url,
+                prebuilt_sha256: install_cmd.prebuilt_sha256,
+                update: install_cmd.update,
+                unchecked_paths: install_cmd.unchecked_paths,
+                offline: install_cmd.offline,
+                proxy: install_cmd.proxy,
+                ssh_key: install_cmd.ssh_key,
+                preset: install_cmd.preset,
+                runtime: install_cmd.runtime,
+                runtime_only: install_cmd.runtime_only,
+                write_env: install_cmd.write_env,
+                post_build: install_cmd.post_build.map(|hook| shell_command(&hook)),
+                cxx_standard: install_cmd.cxx_standard,
+                prefer_static: install_cmd.prefer_static,
+                strip: install_cmd.strip,
+                relocatable: install_cmd.relocatable,
+                jobs: install_cmd.jobs,
+                limit_jobs_by_memory: install_cmd.limit_jobs_by_memory,
+                link_jobs: install_cmd.link_jobs,
+                configure_timeout: install_cmd.configure_timeout.map(Duration::from_secs),
+                generator: install_cmd.generator,
+                toolset: install_cmd.toolset,
+                platform: install_cmd.platform,
+                settings: if install_cmd.auto {
+                    settings.or(BuildConfig::autodetect_backends())
+                } else {
+                    settings
+                },
+                ..BuildConfig::default()
+            };
+            let config = with_defines(config, &install_cmd.defines)?;
+            let (config, progress) = if install_cmd.verbose > 0 {
+                let (sender, events) = mpsc::channel();
+                let config = BuildConfig {
+                    progress: Some(sender),
+                    ..config
+                };
+                (config, Some(print_submodule_progress(events)))
+            } else {
+                (config, None)
+            };
+            let result = build(config);
+            if let Some(progress) = progress {
+                let _ = progress.join();
+            }
+            let result = result?;
+            if install_cmd.smoke_test {
+                result.smoke_test()?;
+            }
+            if let Some(warnings) = result.warnings {
+                for message in &warnings.messages {
+                    println!("{}", message);
+                }
+                println!("the build emitted {} warning(s)", warnings.count);
+            }
+            if let Some(tests) = result.tests {
+                println!("{} C++ test(s) passed", tests.passed);
+            }
+            for library in &result.stripped {
+                println!(
+                    "stripped {} from {} to {} bytes",
+                    library.path.display(),
+                    library.size_before,
+                    library.size_after
+                );
+            }
+            if let Some(graph) = result.dependency_graph {
+                println!("the dependency graph is in {}", graph.display());
+            }
+            if let Some(docs) = result.docs {
+                println!("the documentation is in {}", docs.display());
+            }
+            Ok(())
+        }
+        TVMBuildArgs::Lock(lock_cmd) => {
+            let config = BuildConfig {
+                branch: Some(lock_cmd.revision.revision),
+                repository: lock_cmd.repository.or(lock_cmd.revision.repository),
+                output_path: lock_cmd.output_path,
+                target: lock_cmd.target,
+                unchecked_paths: lock_cmd.unchecked_paths,
+                settings: lock_cmd.settings.or(UserSettings::from_env()?),
+                ..BuildConfig::default()
+            };
+            let config = with_defines(config, &lock_cmd.defines)?;
+            let manifest = tvm_build::lock(config)?;
+            println!("{}", serde_json::to_string_pretty(&manifest).unwrap());
+            Ok(())
+        }
+        TVMBuildArgs::Check(check_cmd) => {
+            let config = BuildConfig {
+                branch: Some(check_cmd.revision.revision),
+                repository: check_cmd.repository.or(check_cmd.revision.repository),
+                output_path: check_cmd.output_path,
+                target: check_cmd.target,
+                unchecked_paths: check_cmd.unchecked_paths,
+                settings: check_cmd.settings.or(UserSettings::from_env()?),
+                ..BuildConfig::default()
+            };
+            let config = with_defines(config, &check_cmd.defines)?;
+            let warnings = tvm_build::check(config)?;
+            for warning in &warnings {
+                println!("{}", warning);
+            }
+            println!("the settings are valid, CMake reported {} warning(s)", warnings.len());
+            Ok(())
+        }
+        TVMBuildArgs::List(list_cmd) => {
+            for revision in tvm_build::list_revisions(list_cmd.output_path)? {
+                let status = if revision.is_built()? {
+                    "built"
+                } else {
+                    "not built"
+                };
+                println!(
+                    "{}\t{}\t{}",
+                    revision.name(),
+                    status,
+                    format_size(revision.disk_usage()?)
+                );
+            }
+            Ok(())
+        }
+        TVMBuildArgs::Gc(gc_cmd) => {
+            let removed = tvm_build::gc(gc_cmd.revision, gc_cmd.output_path)?;
+            println!("removed {} stale file(s)", removed);
+            Ok(())
+        }
+        TVMBuildArgs::Env(env_cmd) => {
+            print!("{}", tvm_build::env(env_cmd.revision, env_cmd.output_path)?);
+            Ok(())
+        }
+        TVMBuildArgs::Diff(diff_cmd) => {
+            let a = Revision::new(diff_cmd.revision_a, diff_cmd.output_path.clone())?
+                .with_detected_build_layout();
+            let b = Revision::new(diff_cmd.revision_b, diff_cmd.output_path)?
+                .with_detected_build_layout();
+            let unset = || "<unset>".to_string();
+            for diff in tvm_build::diff_revisions(&a, &b)? {
+                println!(
+                    "{}: {} -> {}",
+                    diff.key,
+                    diff.a.unwrap_or_else(unset),
+                    diff.b.unwrap_or_else(unset)
+                );
+            }
             Ok(())
         }
         TVMBuildArgs::Uninstall(uninstall_cmd) => {
@@ -67,9 +614,49 @@ fn main() -> anyhow::Result<()> {
             Ok(())
         }
         TVMBuildArgs::VersionConfig(version_cmd) => {
-            let config = tvm_build::version_config(version_cmd.revision)?;
+            let config = tvm_build::version_config(version_cmd.revision, version_cmd.python_subpath)?;
             println!("{}", serde_json::to_string(&config).unwrap());
             Ok(())
         }
+        TVMBuildArgs::Config(config_cmd) => {
+            let config = BuildConfig {
+                unchecked_paths: config_cmd.unchecked_paths,
+                settings: config_cmd.settings.or(UserSettings::from_env()?),
+                ..BuildConfig::default()
+            };
+            let config = with_defines(config, &config_cmd.defines)?;
+            match config_cmd.format {
+                ConfigFormat::CMake => print!("{}", config.to_config_cmake()),
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_define_splits_on_first_equals() {
+        assert_eq!(
+            parse_define("USE_CUDA=ON"),
+            Ok(("USE_CUDA".to_string(), "ON".to_string()))
+        );
+        assert_eq!(
+            parse_define("CMAKE_CXX_FLAGS=-DA=1 -DB=2"),
+            Ok(("CMAKE_CXX_FLAGS".to_string(), "-DA=1 -DB=2".to_string()))
+        );
+        assert_eq!(
+            parse_define("USE_LLVM="),
+            Ok(("USE_LLVM".to_string(), String::new()))
+        );
+    }
+
+    #[test]
+    fn parse_define_rejects_malformed() {
+        assert!(parse_define("=ON").is_err());
+        assert!(parse_define(" =ON").is_err());
+        assert!(parse_define("USE_CUDA").is_err());
     }
 }
@@ -0,0 +1,124 @@
+//! Building TVM from a consumer's `build.rs`, see `tvm_build::build_from_env`.
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::core::{BuildConfig, BuildResult, Error, UserSettings};
+use super::manifest::BuildManifest;
+
+/// The revision to build, by default `main`.
+pub const REVISION_VAR: &str = "TVM_BUILD_REVISION";
+/// The repository to clone, by default the upstream TVM repository.
+pub const REPOSITORY_VAR: &str = "TVM_BUILD_REPOSITORY";
+/// The directory to build in, by default `~/.tvm_build`.
+pub const OUTPUT_PATH_VAR: &str = "TVM_BUILD_OUTPUT_PATH";
+/// The target triple to build for, by default the host.
+pub const TARGET_VAR: &str = "TVM_BUILD_TARGET";
+/// The path of the configuration file, by default `tvm-build.toml` in the crate root.
+///
+/// Settings are also read from `TVM_BUILD_<SETTING>` variables, see `UserSettings::from_env`.
+pub const CONFIG_VAR: &str = "TVM_BUILD_CONFIG";
+
+const CONFIG_FILE: &str = "tvm-build.toml";
+
+/// The contents of a `tvm-build.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BuildFile {
+    revision: Option<String>,
+    repository: Option<String>,
+    output_path: Option<String>,
+    target: Option<String>,
+    /// The library to link against, by default `tvm_runtime`.
+    library: Option<String>,
+    settings: UserSettings,
+}
+
+impl BuildFile {
+    fn read(path: &Path) -> Result<BuildFile, Error> {
+        let contents = std::fs::read_to_string(path)?;
+        toml::from_str(&contents)
+            .map_err(|e| Error::InvalidSettings(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// Read an environment variable, telling Cargo to rerun the build script when it changes.
+fn env_var(name: &str) -> Option<String> {
+    println!("cargo:rerun-if-env-changed={}", name);
+    std::env::var(name).ok()
+}
+
+/// The configuration file to read, if there is one.
+fn config_path() -> Option<PathBuf> {
+    match env_var(CONFIG_VAR) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => {
+            let manifest_dir = PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR")?);
+            Some(manifest_dir.join(CONFIG_FILE)).filter(|path| path.exists())
+        }
+    }
+}
+
+pub(crate) fn build_from_env() -> Result<BuildResult, Error> {
+    let file = match config_path() {
+        Some(path) => {
+            println!("cargo:rerun-if-changed={}", path.display());
+            BuildFile::read(&path)?
+        }
+        None => BuildFile::default(),
+    };
+
+    let config = BuildConfig {
+        branch: env_var(REVISION_VAR).or(file.revision),
+        repository: env_var(REPOSITORY_VAR).or(file.repository),
+        output_path: env_var(OUTPUT_PATH_VAR).or(file.output_path),
+        target: env_var(TARGET_VAR).or(file.target),
+        settings: UserSettings::from_env()?.or(file.settings),
+        ..BuildConfig::default()
+    };
+
+    let result = super::build(config)?;
+    match &file.library {
+        Some(library) => result.emit_cargo_metadata_for(library),
+        None => result.emit_cargo_metadata(),
+    }
+    Ok(result)
+}
+
+/// The parts of the source tree which affect the build, relative to it. The whole tree
+/// isn't watched as an in-source build tree is inside it.
+const WATCHED_SOURCES: &[&str] = &["CMakeLists.txt", "cmake", "include", "src"];
+
+impl BuildResult {
+    /// Print the directives linking the crate being built against `libtvm_runtime`,
+    /// for use in a `build.rs`.
+    ///
+    /// The build script is rerun when the revision is rebuilt or its source changes, and
+    /// the include directories are emitted as `cargo:include`.
+    pub fn emit_cargo_metadata(&self) {
+        self.emit_cargo_metadata_for("tvm_runtime")
+    }
+
+    /// Like `emit_cargo_metadata`, linking against `library`, e.g. `tvm` for the full
+    /// compiler rather than only the runtime.
+    pub fn emit_cargo_metadata_for(&self, library: &str) {
+        let revision = &self.revision;
+        println!("cargo:rerun-if-changed={}", BuildManifest::path(revision).display());
+        let source_path = revision.source_path();
+        for source in WATCHED_SOURCES {
+            let path = source_path.join(source);
+            if path.exists() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+        println!("cargo:rustc-link-search=native={}", revision.lib_path().display());
+        println!("cargo:rustc-link-lib=dylib={}", library);
+
+        let includes: Vec<String> = self
+            .cflags()
+            .iter()
+            .map(|flag| flag.trim_start_matches("-I").to_string())
+            .collect();
+        println!("cargo:include={}", includes.join(if cfg!(windows) { ";" } else { ":" }));
+    }
+}
@@ -0,0 +1,109 @@
+//! A fluent way of constructing a `BuildConfig`, which keeps working as fields are
+//! added where a struct literal would need updating.
+use std::{path::PathBuf, process::Command, sync::mpsc::Sender, time::Duration};
+
+use super::core::{BuildConfig, BuildLayout, Preset, Profile, Runtime, UserSettings};
+use super::progress::BuildEvent;
+
+/// Generate a setter per field. `option` fields take the value itself and wrap it in
+/// `Some`, `value` fields take the field's type as is.
+macro_rules! setters {
+    ($($kind:ident $field:ident: $ty:ty),* $(,)?) => {
+        $(setters!(@$kind $field: $ty);)*
+    };
+    (@option $field:ident: $ty:ty) => {
+        #[doc = concat!("Set `BuildConfig::", stringify!($field), "`.")]
+        pub fn $field<T: Into<$ty>>(mut self, $field: T) -> Self {
+            self.config.$field = Some($field.into());
+            self
+        }
+    };
+    (@value $field:ident: $ty:ty) => {
+        #[doc = concat!("Set `BuildConfig::", stringify!($field), "`.")]
+        pub fn $field(mut self, $field: $ty) -> Self {
+            self.config.$field = $field;
+            self
+        }
+    };
+}
+
+/// Builds a `BuildConfig` starting from the defaults, for example
+/// `BuildConfig::builder().repository(url).branch("main").clean(true).build()`.
+#[derive(Debug, Default)]
+pub struct BuildConfigBuilder {
+    config: BuildConfig,
+}
+
+impl BuildConfig {
+    /// Start building a configuration from the defaults.
+    pub fn builder() -> BuildConfigBuilder {
+        BuildConfigBuilder::default()
+    }
+}
+
+impl BuildConfigBuilder {
+    setters! {
+        option repository: String,
+        option repository_path: String,
+        option output_path: String,
+        value ephemeral: bool,
+        option branch: String,
+        value keep_going: bool,
+        value collect_warnings: bool,
+        value force: bool,
+        option log_level: tracing::Level,
+        value clean: bool,
+        option target: String,
+        value build_layout: BuildLayout,
+        value profile: Profile,
+        option config_cmake: PathBuf,
+        option install_python: PathBuf,
+        option prebuilt_url: String,
+        option prebuilt_sha256: String,
+        option source_archive: PathBuf,
+        option clone_depth: u32,
+        option shallow_since: String,
+        option shallow_since_tag: String,
+        value fallback_to_full: bool,
+        value locked: bool,
+        value update: bool,
+        value run_cpp_tests: bool,
+        value build_docs: bool,
+        value dependency_graph: bool,
+        value track_remote_submodules: Vec<String>,
+        option preset: Preset,
+        option runtime: Runtime,
+        value runtime_only: bool,
+        option cxx_standard: u32,
+        value prefer_static: bool,
+        value strip: bool,
+        value relocatable: bool,
+        option jobs: usize,
+        value limit_jobs_by_memory: bool,
+        option link_jobs: usize,
+        option configure_timeout: Duration,
+        option generator: String,
+        option toolset: String,
+        option platform: String,
+        value offline: bool,
+        option proxy: String,
+        option ssh_key: PathBuf,
+        value write_env: bool,
+        option post_build: Command,
+        value extra_defines: Vec<(String, String)>,
+        value unchecked_paths: Vec<String>,
+        option progress: Sender<BuildEvent>,
+        value settings: UserSettings,
+    }
+
+    /// Add a CMake define to `BuildConfig::extra_defines`.
+    pub fn define<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.config.extra_defines.push((key.into(), value.into()));
+        self
+    }
+
+    /// The configuration, it isn't validated until it is built with.
+    pub fn build(self) -> BuildConfig {
+        self.config
+    }
+}
@@ -0,0 +1,38 @@
+//! Integrity checks for downloaded artifacts.
+use std::{fs::File, io::Read, path::Path};
+
+use sha2::{Digest, Sha256};
+
+use super::core::Error;
+
+/// Compute the SHA-256 digest of a file as lowercase hex, reading it in chunks so
+/// large archives aren't held in memory.
+pub(crate) fn sha256_file(path: &Path) -> Result<String, Error> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Verify a file matches the expected SHA-256 digest, deleting it if it doesn't so a
+/// corrupt or tampered download is never used.
+pub(crate) fn verify_file(path: &Path, expected: &str) -> Result<(), Error> {
+    let expected = expected.trim().to_ascii_lowercase();
+    let actual = sha256_file(path)?;
+
+    if actual == expected {
+        Ok(())
+    } else {
+        std::fs::remove_file(path)?;
+        Err(Error::ChecksumMismatch { expected, actual })
+    }
+}
@@ -0,0 +1,392 @@
+//! A minimal driver for CMake.
+//!
+//! This mirrors the parts of the `cmake` crate's `Config` we rely on, with two
+//! differences which matter when building a project as large as TVM: failures
+//! are reported as errors instead of panics, and the output of the tools is handed
+//! back line by line so it can be logged and used to track progress.
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, warn};
+
+use super::core::Error;
+
+/// The number of lines of output kept to report when a command fails.
+const OUTPUT_TAIL_LINES: usize = 30;
+
+/// The file the output of the configure step is written to, in the binary directory.
+const CONFIGURE_LOG: &str = "tvm-build-configure.log";
+
+pub struct Config {
+    path: PathBuf,
+    generator: Option<String>,
+    generator_toolset: Option<String>,
+    platform: Option<String>,
+    defines: Vec<(String, String)>,
+    out_dir: Option<PathBuf>,
+    profile: Option<String>,
+    keep_going: bool,
+    jobs: Option<usize>,
+    link_jobs: Option<usize>,
+    configure_timeout: Option<Duration>,
+    very_verbose: bool,
+}
+
+impl Config {
+    /// Create a configuration for the CMake project in `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Config {
+        Config {
+            path: path.as_ref().to_path_buf(),
+            generator: None,
+            generator_toolset: None,
+            platform: None,
+            defines: vec![],
+            out_dir: None,
+            profile: None,
+            keep_going: false,
+            jobs: None,
+            link_jobs: None,
+            configure_timeout: None,
+            very_verbose: false,
+        }
+    }
+
+    pub fn generator<T: Into<String>>(&mut self, generator: T) -> &mut Config {
+        self.generator = Some(generator.into());
+        self
+    }
+
+    /// The toolset to use with generators which support one (`-T`), e.g. `v142`.
+    pub fn generator_toolset<T: Into<String>>(&mut self, toolset: T) -> &mut Config {
+        self.generator_toolset = Some(toolset.into());
+        self
+    }
+
+    /// The platform to use with generators which support one (`-A`), e.g. `x64`.
+    pub fn platform<T: Into<String>>(&mut self, platform: T) -> &mut Config {
+        self.platform = Some(platform.into());
+        self
+    }
+
+    pub fn define<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> &mut Config {
+        self.defines.push((key.into(), value.into()));
+        self
+    }
+
+    /// The directory to install into, the project is configured and built in
+    /// its `build` subdirectory.
+    pub fn out_dir<P: AsRef<Path>>(&mut self, out_dir: P) -> &mut Config {
+        self.out_dir = Some(out_dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn profile(&mut self, profile: &str) -> &mut Config {
+        self.profile = Some(profile.to_string());
+        self
+    }
+
+    /// Keep building as much as possible after a compile error, only supported by
+    /// the Makefile and Ninja generators.
+    pub fn keep_going(&mut self, keep_going: bool) -> &mut Config {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// The number of jobs to build with in parallel, by default the build tool decides.
+    pub fn jobs(&mut self, jobs: usize) -> &mut Config {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Limit how many link steps run in parallel, only supported by the Ninja generator.
+    pub fn link_jobs(&mut self, link_jobs: usize) -> &mut Config {
+        self.link_jobs = Some(link_jobs);
+        self
+    }
+
+    /// Stop the configure step if it runs for longer than `timeout`.
+    pub fn configure_timeout(&mut self, timeout: Duration) -> &mut Config {
+        self.configure_timeout = Some(timeout);
+        self
+    }
+
+    pub fn very_verbose(&mut self, very_verbose: bool) -> &mut Config {
+        self.very_verbose = very_verbose;
+        self
+    }
+
+    fn install_dir(&self) -> PathBuf {
+        self.out_dir.clone().unwrap_or_else(|| self.path.join("out"))
+    }
+
+    /// The directory CMake configures and builds the project in.
+    pub fn binary_dir(&self) -> PathBuf {
+        self.install_dir().join("build")
+    }
+
+    fn is_visual_studio(&self) -> bool {
+        self.generator
+            .as_deref()
+            .is_some_and(|generator| generator.starts_with("Visual Studio"))
+    }
+
+    /// Both the single and multi-config Ninja generators.
+    fn is_ninja(&self) -> bool {
+        self.generator
+            .as_deref()
+            .is_some_and(|generator| generator.starts_with("Ninja"))
+    }
+
+    /// The build type, and for multi-config generators the directory built into.
+    pub fn profile_name(&self) -> &str {
+        self.profile.as_deref().unwrap_or("Debug")
+    }
+
+    /// Remove the CMake cache so the next configure starts from scratch, keeping the
+    /// build outputs.
+    pub fn clear_cache(&self) -> Result<(), Error> {
+        let cache = self.binary_dir().join("CMakeCache.txt");
+        if cache.exists() {
+            std::fs::remove_file(cache)?;
+        }
+        Ok(())
+    }
+
+    /// Remove everything CMake configured and built.
+    pub fn clear_binary_dir(&self) -> Result<(), Error> {
+        let binary_dir = self.binary_dir();
+        if binary_dir.exists() {
+            std::fs::remove_dir_all(binary_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Run CMake's configure step, passing each line of output to `on_line`.
+    pub fn configure<F: FnMut(&str)>(&self, on_line: F) -> Result<(), Error> {
+        let binary_dir = self.binary_dir();
+        std::fs::create_dir_all(&binary_dir)?;
+
+        let mut cmd = Command::new("cmake");
+        cmd.current_dir(&binary_dir).arg(&self.path);
+
+        if let Some(generator) = &self.generator {
+            cmd.arg("-G").arg(generator);
+        }
+
+        // Only the Visual Studio generators support selecting a toolset and platform.
+        if self.is_visual_studio() {
+            if let Some(toolset) = &self.generator_toolset {
+                cmd.arg("-T").arg(toolset);
+            }
+            if let Some(platform) = &self.platform {
+                cmd.arg("-A").arg(platform);
+            }
+        } else if self.generator_toolset.is_some() || self.platform.is_some() {
+            warn!(generator = ?self.generator, "ignoring the toolset and platform, they are only supported by Visual Studio generators");
+        }
+
+        cmd.arg(format!(
+            "-DCMAKE_INSTALL_PREFIX={}",
+            self.install_dir().display()
+        ));
+        cmd.arg(format!("-DCMAKE_BUILD_TYPE={}", self.profile_name()));
+
+        for (key, value) in &self.defines {
+            cmd.arg(format!("-D{}={}", key, value));
+        }
+
+        if let Some(link_jobs) = self.link_jobs {
+            if self.is_ninja() {
+                cmd.arg(format!("-DCMAKE_JOB_POOLS=link_pool={}", link_jobs));
+                cmd.arg("-DCMAKE_JOB_POOL_LINK=link_pool");
+            } else {
+                warn!(generator = ?self.generator, "limiting link jobs is only supported by the Ninja generator");
+            }
+        }
+
+        if self.very_verbose {
+            cmd.arg("-DCMAKE_VERBOSE_MAKEFILE:BOOL=ON");
+        }
+
+        let log = binary_dir.join(CONFIGURE_LOG);
+        let timeout = self.configure_timeout.map(|timeout| ("configure", timeout));
+        run_logged(&mut cmd, Some(&log), timeout, on_line)
+    }
+
+    /// Build and install the configured project, passing each line of output to `on_line`.
+    pub fn build<F: FnMut(&str)>(&self, on_line: F) -> Result<(), Error> {
+        self.build_target("install", on_line)
+    }
+
+    /// Build a single target of the configured project, passing each line of output
+    /// to `on_line`.
+    pub fn build_target<F: FnMut(&str)>(&self, target: &str, on_line: F) -> Result<(), Error> {
+        let mut cmd = Command::new("cmake");
+        cmd.current_dir(self.binary_dir())
+            .arg("--build")
+            .arg(".")
+            .arg("--target")
+            .arg(target)
+            .arg("--config")
+            .arg(self.profile_name());
+
+        if let Some(jobs) = self.jobs {
+            cmd.arg("--parallel").arg(jobs.to_string());
+        }
+
+        let mut build_args = vec![];
+        if self.keep_going {
+            match self.generator.as_deref() {
+                _ if self.is_ninja() => build_args.extend(["-k".to_string(), "0".to_string()]),
+                Some(generator) if generator.ends_with("Makefiles") => {
+                    build_args.push("-k".to_string())
+                }
+                generator => warn!(?generator, "keep going is not supported by the generator"),
+            }
+        }
+
+        if !build_args.is_empty() {
+            cmd.arg("--").args(&build_args);
+        }
+
+        run(&mut cmd, on_line)
+    }
+
+    /// Run the project's tests with CTest, passing each line of output to `on_line`.
+    pub fn test<F: FnMut(&str)>(&self, on_line: F) -> Result<(), Error> {
+        let mut cmd = Command::new("ctest");
+        cmd.current_dir(self.binary_dir())
+            .arg("--output-on-failure")
+            .arg("-C")
+            .arg(self.profile_name());
+
+        run(&mut cmd, on_line)
+    }
+
+    /// Write the dependency graph of the configured project to `path` in Graphviz's
+    /// dot format, from Ninja's build graph or otherwise from CMake's target graph.
+    pub fn dependency_graph(&self, path: &Path) -> Result<(), Error> {
+        if self.is_ninja() {
+            let mut cmd = Command::new("ninja");
+            cmd.current_dir(self.binary_dir()).args(["-t", "graph"]);
+            debug!(command = ?cmd, "running");
+            let output = cmd.output()?;
+            if !output.status.success() {
+                return Err(Error::CMakeFailed {
+                    command: format!("{:?}", cmd),
+                    status: output.status,
+                    output: String::from_utf8_lossy(&output.stderr).into_owned(),
+                    log: None,
+                });
+            }
+            std::fs::write(path, output.stdout)?;
+            Ok(())
+        } else {
+            let mut cmd = Command::new("cmake");
+            cmd.current_dir(self.binary_dir())
+                .arg(format!("--graphviz={}", path.display()))
+                .arg(".");
+            run(&mut cmd, |line| debug!(target: "cmake", "{}", line))
+        }
+    }
+}
+
+/// Remove the outputs which are no longer part of the build graph, in a build tree
+/// configured with the Ninja generator.
+pub fn clean_dead<F: FnMut(&str)>(binary_dir: &Path, on_line: F) -> Result<(), Error> {
+    let mut cmd = Command::new("ninja");
+    cmd.current_dir(binary_dir).args(["-t", "cleandead"]);
+    run(&mut cmd, on_line)
+}
+
+/// Run `cmd` to completion, merging its stdout and stderr into `on_line`.
+fn run<F: FnMut(&str)>(cmd: &mut Command, on_line: F) -> Result<(), Error> {
+    run_logged(cmd, None, None, on_line)
+}
+
+/// Like `run`, also writing the output to `log` so it can be inspected in full when
+/// the command fails.
+///
+/// With a `timeout`, the name of the phase the command runs and how long it may take,
+/// the command is killed once it runs for longer and `Error::Timeout` is returned.
+fn run_logged<F: FnMut(&str)>(
+    cmd: &mut Command,
+    log: Option<&Path>,
+    timeout: Option<(&str, Duration)>,
+    mut on_line: F,
+) -> Result<(), Error> {
+    debug!(command = ?cmd, "running");
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+
+    let (sender, receiver) = mpsc::channel();
+    let outputs: Vec<Box<dyn Read + Send>> = vec![
+        Box::new(child.stdout.take().expect("stdout is piped")),
+        Box::new(child.stderr.take().expect("stderr is piped")),
+    ];
+
+    for output in outputs {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(output).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    drop(sender);
+    let mut log_file = log.map(std::fs::File::create).transpose()?;
+    let mut tail = VecDeque::with_capacity(OUTPUT_TAIL_LINES);
+    let deadline = timeout.map(|(_, timeout)| Instant::now() + timeout);
+    loop {
+        let received = match deadline {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        let line = match (received, timeout) {
+            (Ok(line), _) => line,
+            (Err(RecvTimeoutError::Timeout), Some((phase, timeout))) => {
+                warn!(phase, timeout_secs = timeout.as_secs(), "timed out, stopping the command");
+                // The command may already have exited on its own.
+                let _ = child.kill();
+                child.wait()?;
+                return Err(Error::Timeout {
+                    phase: phase.to_string(),
+                    timeout,
+                });
+            }
+            (Err(_), _) => break,
+        };
+
+        on_line(&line);
+        if let Some(log_file) = &mut log_file {
+            writeln!(log_file, "{}", line)?;
+        }
+        if tail.len() == OUTPUT_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::CMakeFailed {
+            command: format!("{:?}", cmd),
+            status,
+            output: Vec::from(tail).join("\n"),
+            log: log.map(Path::to_path_buf),
+        })
+    }
+}
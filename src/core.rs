@@ -1,17 +1,58 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsString,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::mpsc::Sender,
+    time::Duration,
+};
 use structopt::StructOpt;
 
-use cmake;
-use dirs;
-use git2::build::RepoBuilder;
+use git2::{
+    build::RepoBuilder, Cred, CredentialType, FetchOptions, ProxyOptions, RemoteCallbacks,
+    SubmoduleUpdateOptions,
+};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
-use tracing::{self, info};
+use tracing::{self, debug, info, level_filters::LevelFilter, warn};
 
+use super::archive::{self, Format};
+use super::checksum;
+use super::cmake;
+use super::detect;
+use super::lfs;
+use super::manifest::{BuildManifest, BuildStatus};
+use super::progress::{self, BuildEvent};
+use super::strip::{self, StrippedLibrary};
 use super::targets::Target;
+#[cfg(target_os = "windows")]
+use super::windows;
 
-const TVM_REPO: &'static str = "https://github.com/apache/tvm";
-const DEFAULT_BRANCH: &'static str = "main";
+const TVM_REPO: &str = "https://github.com/apache/tvm";
+const DEFAULT_BRANCH: &str = "main";
 
+/// The C++ standards `CMAKE_CXX_STANDARD` accepts which TVM can plausibly build with.
+const CXX_STANDARDS: &[u32] = &[11, 14, 17, 20, 23];
+
+/// The include directories of the submodules TVM's headers depend on, relative to
+/// the source tree.
+const THIRD_PARTY_INCLUDES: &[&str] = &["3rdparty/dlpack/include", "3rdparty/dmlc-core/include"];
+
+/// The file the dependency graph is written to, in the revision directory.
+const DEPENDENCY_GRAPH: &str = "dependencies.dot";
+
+/// The prefix of the environment variables read by `UserSettings::from_env`.
+const SETTINGS_ENV_PREFIX: &str = "TVM_BUILD_";
+
+/// The environment variable holding a token to authenticate to HTTPS remotes with.
+const GIT_TOKEN_VAR: &str = "GIT_TOKEN";
+
+/// The number of warning messages kept when collecting warnings.
+const MAX_WARNING_MESSAGES: usize = 20;
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("{0}")]
@@ -20,18 +61,101 @@ pub enum Error {
     IoError(#[from] std::io::Error),
     #[error("the directory does not exist: {0}")]
     DirectoryNotFound(String),
+    #[error("the file does not exist: {0}")]
+    FileNotFound(String),
+    /// The revision was found but checking it out failed, for example because of
+    /// conflicting local changes, as opposed to `RevisionNotFound`.
+    #[error("{revision} was found but could not be checked out: {source}")]
+    CheckoutFailed {
+        revision: String,
+        source: git2::Error,
+    },
+    /// An existing checkout has local changes to tracked files which checking out
+    /// another commit would discard.
+    #[error("{} has uncommitted changes which checking out {revision} would discard, commit or revert them first", path.display())]
+    DirtyWorkingTree { path: PathBuf, revision: String },
     #[error("the requested revision ({revision}) and repository ({repository}) combination does not exist.")]
     RevisionNotFound {
         revision: String,
         repository: String,
     },
+    #[error("the repository ({repository}) does not exist or is not reachable.")]
+    RepositoryNotFound { repository: String },
+    #[error("installing the TVM Python package failed with {status}:\n{output}")]
+    PythonInstallFailed {
+        status: std::process::ExitStatus,
+        output: String,
+    },
+    #[error("doxygen was not found, install it to build the documentation")]
+    DoxygenNotFound,
+    #[error("building the documentation failed with {status}:\n{output}")]
+    DocsFailed {
+        status: std::process::ExitStatus,
+        output: String,
+    },
+    #[error("no usable Hexagon SDK or toolchain at {path}: {message}")]
+    HexagonSdkNotFound { path: PathBuf, message: String },
+    #[error("the smoke test of {library} failed: {message}")]
+    SmokeTestFailed { library: PathBuf, message: String },
+    #[error("cross compiling to `{0}` is not supported")]
+    UnsupportedTarget(String),
+    #[error("building for `{target}` requires a {host} host")]
+    RequiresHost { target: String, host: String },
+    #[error("the compiler `{compiler}` for target `{target}` could not be found, install the cross toolchain or point CC_{target}/CXX_{target} at it")]
+    ToolchainNotFound { target: String, compiler: String },
+    #[error("{failed} of TVM's C++ tests failed")]
+    TestsFailed { failed: usize },
+    #[error("`{0}` is not a valid revision name, name a branch or tag starting with `-` in full as `refs/heads/<branch>` or `refs/tags/<tag>`, or give its commit SHA")]
+    InvalidRevision(String),
+    #[error("`{command}` failed: {message}")]
+    GitFailed { command: String, message: String },
+    #[error("the commit {commit} is older than the history which was cloned, clone more history or the full repository, or enable `fallback_to_full`")]
+    OutsideShallowHistory { commit: String },
+    #[error("the archive {archive} could not be used: {message}")]
+    InvalidArchive { archive: PathBuf, message: String },
+    #[error("{} doesn't look like a TVM source tree, it has no CMakeLists.txt", path.display())]
+    NotSourceTree { path: PathBuf },
+    #[error("the path {} given for {option} does not exist, if it isn't a path add {option} to `unchecked_paths`", path.display())]
+    SettingPathNotFound { option: String, path: PathBuf },
+    #[error("the post build hook `{command}` failed with {status}")]
+    PostBuildHookFailed {
+        command: String,
+        status: std::process::ExitStatus,
+    },
+    #[error("invalid settings: {0}")]
+    InvalidSettings(String),
+    #[error("the repository at {0} stores files in git LFS, which requires the `git-lfs` feature and the `git lfs` client")]
+    GitLfsRequired(String),
+    #[error("checksum mismatch, expected SHA-256 {expected} but got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("downloading {url} failed: {message}")]
+    DownloadFailed { url: String, message: String },
+    #[error("`{command}` failed with {status}, the output ended with:\n{output}{}", log_hint(.log))]
+    CMakeFailed {
+        command: String,
+        status: std::process::ExitStatus,
+        /// The last lines of output.
+        output: String,
+        /// Where the full output was written, if it was.
+        log: Option<PathBuf>,
+    },
+    #[error("the {phase} step didn't finish within {}s and was stopped", .timeout.as_secs())]
+    Timeout { phase: String, timeout: Duration },
+}
+
+/// Point at the full output of a failed command.
+fn log_hint(log: &Option<PathBuf>) -> String {
+    match log {
+        Some(log) => format!("\nthe full output is in {}", log.display()),
+        None => String::new(),
+    }
 }
 
 /// Many TVM CMake settings are either OFF (disabled), ON (with auto detection) or
 /// a path implying on with a fixed configuration.
 ///
 /// This enumeration represents all cases in a more Rust friendly way.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum CMakeSetting {
     On,
     Off,
@@ -50,6 +174,33 @@ impl FromStr for CMakeSetting {
     }
 }
 
+impl Serialize for CMakeSetting {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.as_string_value())
+    }
+}
+
+impl<'de> Deserialize<'de> for CMakeSetting {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        // Parsing is infallible, anything that is not ON/OFF is a path.
+        Ok(value.parse().unwrap())
+    }
+}
+
+impl JsonSchema for CMakeSetting {
+    fn schema_name() -> String {
+        "CMakeSetting".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = String::json_schema(gen).into_object();
+        schema.metadata().description =
+            Some("Either \"ON\", \"OFF\" or a path implying ON with a fixed configuration.".into());
+        schema.into()
+    }
+}
+
 /// Convert something into a value that can be used in `cmake::Config::define`.
 trait CMakeSettingValue {
     fn as_string_value(&self) -> String;
@@ -83,7 +234,8 @@ impl CMakeSettingValue for &CMakeSetting {
     }
 }
 
-#[derive(Debug, Default, StructOpt)]
+#[derive(Debug, Clone, Default, StructOpt, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct UserSettings {
     // TVM Build Configuration Options
     /// Build with the CUDA support enabled.
@@ -106,8 +258,14 @@ pub struct UserSettings {
     #[structopt(long)]
     pub use_hexagon_device: Option<bool>,
     /// Path to the Hexagon SDK root (required for Hexagon support in TVM runtime or for building TVM runtime for Hexagon.
+    /// Formerly misspelled `use_heaxgon_dsk`, which is still accepted.
+    #[structopt(long, alias = "use-heaxgon-dsk")]
+    #[serde(alias = "use_heaxgon_dsk")]
+    pub use_hexagon_sdk: Option<PathBuf>,
+    /// Path to the Hexagon toolchain, the directory containing `bin/hexagon-clang++`, by
+    /// default the newest toolchain bundled with the Hexagon SDK.
     #[structopt(long)]
-    pub use_heaxgon_dsk: Option<PathBuf>,
+    pub hexagon_toolchain: Option<PathBuf>,
     /// Whether to enable TVM RPC.
     #[structopt(long)]
     pub use_rpc: Option<bool>,
@@ -233,66 +391,744 @@ pub struct UserSettings {
     /// Build with VITIS-AI Codegen support, defaults to OFF.
     #[structopt(long)]
     pub use_vitis_ai: Option<bool>,
-    // Note(@jroesch): these options are supported by TVM but not exposed by this interface
-    // we instead use defaults.
-    //
-    // Configuration for 3rdparty libraries.
-    // tvm_option(DLPACK_PATH "Path to DLPACK" "3rdparty/dlpack/include")
-    // tvm_option(DMLC_PATH "Path to DMLC" "3rdparty/dmlc-core/include")
-    // tvm_option(RANG_PATH "Path to RANG" "3rdparty/rang/include")
-    // tvm_option(COMPILER_RT_PATH "Path to COMPILER-RT" "3rdparty/compiler-rt")
-    // tvm_option(PICOJSON_PATH "Path to PicoJSON" "3rdparty/picojson")
-
+    /// Build with GoogleTest, required for TVM's C++ unit tests, can also be set to
+    /// the GoogleTest install path.
+    #[structopt(long)]
+    pub use_gtest: Option<CMakeSetting>,
+    /// Build with CUTLASS codegen for NVIDIA GPUs, requires CUDA.
+    #[structopt(long)]
+    pub use_cutlass: Option<CMakeSetting>,
+    /// Build with the FlashInfer attention kernels, requires CUDA.
+    #[structopt(long)]
+    pub use_flashinfer: Option<CMakeSetting>,
+    /// Path to the DLPack headers, defaults to the bundled 3rdparty/dlpack/include.
+    #[structopt(long)]
+    pub dlpack_path: Option<PathBuf>,
+    /// Path to the DMLC headers, defaults to the bundled 3rdparty/dmlc-core/include.
+    #[structopt(long)]
+    pub dmlc_path: Option<PathBuf>,
+    /// Path to the rang headers, defaults to the bundled 3rdparty/rang/include.
+    #[structopt(long)]
+    pub rang_path: Option<PathBuf>,
+    /// Path to compiler-rt, defaults to the bundled 3rdparty/compiler-rt.
+    #[structopt(long)]
+    pub compiler_rt_path: Option<PathBuf>,
+    /// Path to PicoJSON, defaults to the bundled 3rdparty/picojson.
+    #[structopt(long)]
+    pub picojson_path: Option<PathBuf>,
     /// Whether to build static libtvm_runtime.a, the default is to build the dynamic
     /// version: libtvm_runtime.so.
     #[structopt(long)]
     pub build_static_runtime: Option<bool>,
 }
 
-#[derive(Debug)]
+impl UserSettings {
+    /// A JSON schema describing every setting, its type and documentation.
+    ///
+    /// Settings serialized in this shape can be read back with `serde`.
+    pub fn json_schema() -> serde_json::Value {
+        let schema = schemars::schema_for!(UserSettings);
+        serde_json::to_value(schema).expect("schemas are always valid json")
+    }
+
+    /// The settings set by `TVM_BUILD_<SETTING>` environment variables, where `<SETTING>`
+    /// is the uppercased name of a field, e.g. `TVM_BUILD_USE_CUDA=on` or
+    /// `TVM_BUILD_USE_LLVM=/usr/bin/llvm-config`.
+    ///
+    /// Values are parsed like a `CMakeSetting`, so switches take `on` or `off`.
+    pub fn from_env() -> Result<UserSettings, Error> {
+        let mut settings = UserSettings::default();
+        for field in UserSettings::default().fields().keys() {
+            let var = format!("{}{}", SETTINGS_ENV_PREFIX, field.to_ascii_uppercase());
+            if let Ok(value) = std::env::var(&var) {
+                settings.set(field, &value).map_err(|_| {
+                    Error::InvalidSettings(format!("`{}` must be ON or OFF, not `{}`", var, value))
+                })?;
+            }
+        }
+        Ok(settings)
+    }
+
+    /// The settings as a JSON object keyed by field name.
+    fn fields(&self) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => unreachable!("settings serialize to an object"),
+        }
+    }
+
+    /// The settings which may be given a path, keyed by the option they set.
+    fn path_settings(&self) -> [(&'static str, Option<&CMakeSetting>); 12] {
+        [
+            ("USE_CUDA", self.use_cuda.as_ref()),
+            ("USE_OPENCL", self.use_opencl.as_ref()),
+            ("USE_VULKAN", self.use_vulkan.as_ref()),
+            ("USE_METAL", self.use_metal.as_ref()),
+            ("USE_ROCM", self.use_rocm.as_ref()),
+            ("USE_LLVM", self.use_llvm.as_ref()),
+            ("USE_MKL", self.use_mkl.as_ref()),
+            (
+                "USE_ARM_COMPUTE_LIB_GRAPH_RUNTIME",
+                self.use_arm_compute_lib_graph_runtime.as_ref(),
+            ),
+            ("USE_TENSORRT_RUNTIME", self.use_tensorrt_runtime.as_ref()),
+            ("USE_GTEST", self.use_gtest.as_ref()),
+            ("USE_CUTLASS", self.use_cutlass.as_ref()),
+            ("USE_FLASHINFER", self.use_flashinfer.as_ref()),
+        ]
+    }
+
+    /// Set the field named `field` to `value`, parsed like a `CMakeSetting`, returning
+    /// whether there is such a field.
+    fn set(&mut self, field: &str, value: &str) -> Result<bool, Error> {
+        let mut fields = self.fields();
+        if !fields.contains_key(field) {
+            return Ok(false);
+        }
+
+        // The fields are either settings, paths or booleans, try each representation.
+        let mut candidates = vec![serde_json::Value::String(value.to_string())];
+        match value.parse().unwrap() {
+            CMakeSetting::On => candidates.push(serde_json::Value::Bool(true)),
+            CMakeSetting::Off => candidates.push(serde_json::Value::Bool(false)),
+            CMakeSetting::Path(_) => {}
+        }
+
+        for candidate in candidates {
+            fields.insert(field.to_string(), candidate);
+            if let Ok(settings) = serde_json::from_value(serde_json::Value::Object(fields.clone())) {
+                *self = settings;
+                return Ok(true);
+            }
+        }
+
+        Err(Error::InvalidSettings(format!(
+            "`{}` must be ON or OFF, not `{}`",
+            field, value
+        )))
+    }
+
+    /// Combine two sets of settings, preferring the settings set in `self`.
+    pub fn or(self, other: UserSettings) -> UserSettings {
+        UserSettings {
+            use_cuda: self.use_cuda.or(other.use_cuda),
+            use_opencl: self.use_opencl.or(other.use_opencl),
+            use_vulkan: self.use_vulkan.or(other.use_vulkan),
+            use_metal: self.use_metal.or(other.use_metal),
+            use_rocm: self.use_rocm.or(other.use_rocm),
+            rocm_path: self.rocm_path.or(other.rocm_path),
+            use_hexagon_device: self.use_hexagon_device.or(other.use_hexagon_device),
+            use_hexagon_sdk: self.use_hexagon_sdk.or(other.use_hexagon_sdk),
+            hexagon_toolchain: self.hexagon_toolchain.or(other.hexagon_toolchain),
+            use_rpc: self.use_rpc.or(other.use_rpc),
+            use_threads: self.use_threads.or(other.use_threads),
+            use_llvm: self.use_llvm.or(other.use_llvm),
+            use_stackvm_runtime: self.use_stackvm_runtime.or(other.use_stackvm_runtime),
+            use_graph_runtime: self.use_graph_runtime.or(other.use_graph_runtime),
+            use_graph_runtime_debug: self.use_graph_runtime_debug.or(other.use_graph_runtime_debug),
+            use_openmp: self.use_openmp.or(other.use_openmp),
+            use_relay_debug: self.use_relay_debug.or(other.use_relay_debug),
+            use_rtti: self.use_rtti.or(other.use_rtti),
+            use_mscv_mt: self.use_mscv_mt.or(other.use_mscv_mt),
+            use_micro: self.use_micro.or(other.use_micro),
+            use_install_dev: self.use_install_dev.or(other.use_install_dev),
+            hide_private_symbols: self.hide_private_symbols.or(other.hide_private_symbols),
+            use_fallback_stl_map: self.use_fallback_stl_map.or(other.use_fallback_stl_map),
+            use_ethosn: self.use_ethosn.or(other.use_ethosn),
+            use_index_default_i64: self.use_index_default_i64.or(other.use_index_default_i64),
+            use_tf_tvmdsoop: self.use_tf_tvmdsoop.or(other.use_tf_tvmdsoop),
+            use_byodt_posit: self.use_byodt_posit.or(other.use_byodt_posit),
+            use_blas: self.use_blas.or(other.use_blas),
+            use_mkl: self.use_mkl.or(other.use_mkl),
+            use_dnnl: self.use_dnnl.or(other.use_dnnl),
+            use_cudnn: self.use_cudnn.or(other.use_cudnn),
+            use_cublas: self.use_cublas.or(other.use_cublas),
+            use_thrust: self.use_thrust.or(other.use_thrust),
+            use_miopen: self.use_miopen.or(other.use_miopen),
+            use_rocblas: self.use_rocblas.or(other.use_rocblas),
+            use_sort: self.use_sort.or(other.use_sort),
+            use_nnpack: self.use_nnpack.or(other.use_nnpack),
+            use_random: self.use_random.or(other.use_random),
+            use_micro_standalone_runtime: self.use_micro_standalone_runtime.or(other.use_micro_standalone_runtime),
+            use_cpp_rpc: self.use_cpp_rpc.or(other.use_cpp_rpc),
+            use_tflite: self.use_tflite.or(other.use_tflite),
+            use_tensorflow_path: self.use_tensorflow_path.or(other.use_tensorflow_path),
+            use_coreml: self.use_coreml.or(other.use_coreml),
+            use_target_onnx: self.use_target_onnx.or(other.use_target_onnx),
+            use_arm_compute_lib: self.use_arm_compute_lib.or(other.use_arm_compute_lib),
+            use_arm_compute_lib_graph_runtime: self.use_arm_compute_lib_graph_runtime.or(other.use_arm_compute_lib_graph_runtime),
+            use_tensorrt_codegen: self.use_tensorrt_codegen.or(other.use_tensorrt_codegen),
+            use_tensorrt_runtime: self.use_tensorrt_runtime.or(other.use_tensorrt_runtime),
+            use_rust_ext: self.use_rust_ext.or(other.use_rust_ext),
+            use_vitis_ai: self.use_vitis_ai.or(other.use_vitis_ai),
+            use_gtest: self.use_gtest.or(other.use_gtest),
+            use_cutlass: self.use_cutlass.or(other.use_cutlass),
+            use_flashinfer: self.use_flashinfer.or(other.use_flashinfer),
+            dlpack_path: self.dlpack_path.or(other.dlpack_path),
+            dmlc_path: self.dmlc_path.or(other.dmlc_path),
+            rang_path: self.rang_path.or(other.rang_path),
+            compiler_rt_path: self.compiler_rt_path.or(other.compiler_rt_path),
+            picojson_path: self.picojson_path.or(other.picojson_path),
+            build_static_runtime: self.build_static_runtime.or(other.build_static_runtime),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct BuildConfig {
     pub repository: Option<String>,
+    /// An existing source tree to build instead of cloning, such as a read-only checkout
+    /// shared between users. It is never written to: nothing is fetched or checked out
+    /// and the build goes to `output_path`.
     pub repository_path: Option<String>,
     pub output_path: Option<String>,
+    /// Build in a temporary directory instead of `output_path`, which is removed when
+    /// the `BuildResult` is dropped. Paths into the build, such as the libraries, are
+    /// only valid while the result is alive.
+    pub ephemeral: bool,
+    /// The branch, tag or commit to build, a full or abbreviated commit SHA is checked
+    /// out after cloning the default branch.
+    ///
+    /// Like git we reject names starting with `-`. Such a branch or tag can still be
+    /// built by naming it in full, as `refs/heads/<branch>` or `refs/tags/<tag>`, or by
+    /// its full commit SHA.
     pub branch: Option<String>,
-    pub verbose: bool,
+    /// Keep compiling unrelated targets after a compile error, to surface all errors at once.
+    pub keep_going: bool,
+    /// Count the compiler warnings emitted during the build, see `BuildResult::warnings`.
+    pub collect_warnings: bool,
+    /// Rebuild even if the source and settings are unchanged since the last build.
+    pub force: bool,
+    /// The level to log the build's progress at, at `TRACE` the build tool also prints
+    /// the commands it runs.
+    ///
+    /// When unset, or when the application embedding the crate already installed a
+    /// subscriber, none is installed and the application's subscriber receives the
+    /// build's events, the commands are then printed when it traces.
+    pub log_level: Option<tracing::Level>,
     pub clean: bool,
+    /// The target triple to cross compile for, by default we build for the host.
+    pub target: Option<String>,
+    /// Where to place the build directory relative to the source.
+    pub build_layout: BuildLayout,
+    /// The CMake build type, `Debug` by default.
+    pub profile: Profile,
+    /// An existing TVM `config.cmake` to build with.
+    ///
+    /// The file is copied into the CMake build directory, which is where TVM looks
+    /// for it, and the defines derived from `settings` are appended to the copy.
+    /// Because the appended `set(...)` calls come last they win whenever both the
+    /// file and `settings` configure the same option.
+    pub config_cmake: Option<PathBuf>,
+    /// After building, install the TVM Python package in development mode using this
+    /// Python interpreter.
+    pub install_python: Option<PathBuf>,
+    /// A base URL to download prebuilt releases from, see `prebuilt::install` for the
+    /// expected layout. When no prebuilt matches the revision and target we build from
+    /// source.
+    pub prebuilt_url: Option<String>,
+    /// The SHA-256 digest the prebuilt archive must match, by default the digest
+    /// published alongside the archive is used.
+    pub prebuilt_sha256: Option<String>,
+    /// Build the source in this `.tar.gz`, `.tar.xz` or `.zip` archive instead of
+    /// cloning the repository, without any git operations.
+    pub source_archive: Option<PathBuf>,
+    /// Only clone this many commits of history, by default the full history is cloned.
+    /// Newly cloned submodules, nested ones included, only fetch the commit they're pinned
+    /// at with the same depth, falling back to a full clone when the server doesn't allow
+    /// fetching a commit by its SHA.
+    pub clone_depth: Option<u32>,
+    /// Only clone the history since this date, e.g. `2021-06-01`, using the git CLI.
+    pub shallow_since: Option<String>,
+    /// Only clone the history since this tag, using the git CLI.
+    pub shallow_since_tag: Option<String>,
+    /// When a shallow clone doesn't contain a commit we need to check out, fetch the
+    /// full history and retry rather than failing.
+    pub fallback_to_full: bool,
+    /// Check out the exact source and submodule commits recorded by `tvm_build::lock`
+    /// or, when the revision wasn't locked, by the last successful build.
+    pub locked: bool,
+    /// Fetch the branch and reset an existing checkout to its tip, updating the
+    /// submodules, rather than building the source as it was first fetched. Commits are
+    /// pinned and never updated.
+    pub update: bool,
+    /// Build and run TVM's C++ unit tests after the build, requires `use_gtest`.
+    pub run_cpp_tests: bool,
+    /// Build the C++ API documentation with doxygen after the build.
+    pub build_docs: bool,
+    /// Write the build's dependency graph in Graphviz's dot format to the revision
+    /// directory, for debugging the structure of the native build.
+    pub dependency_graph: bool,
+    /// Submodules, by name or path, to update to the tip of their remote branch instead
+    /// of the commit the superproject pins, like `git submodule update --remote`.
+    ///
+    /// This is for iterating on a bundled dependency such as dlpack, builds tracking a
+    /// remote aren't reproducible so it is empty by default.
+    pub track_remote_submodules: Vec<String>,
+    /// A preset to base the settings on, settings chosen explicitly take precedence.
+    pub preset: Option<Preset>,
+    /// The runtime to build, setting the combination of executor options it needs.
+    ///
+    /// The individual `use_graph_runtime` and `use_graph_runtime_debug` settings may
+    /// still be set for finer control, as long as they agree with the runtime.
+    pub runtime: Option<Runtime>,
+    /// Build only the runtime library, `libtvm_runtime`, rather than the whole compiler.
+    pub runtime_only: bool,
+    /// The C++ standard to build with, e.g. `17`, by default TVM picks its own.
+    pub cxx_standard: Option<u32>,
+    /// Link third party dependencies statically where TVM supports it, for producing
+    /// self-contained artifacts.
+    ///
+    /// This sets `BUILD_SHARED_LIBS=OFF` for the bundled dependencies and links LLVM
+    /// statically (`llvm-config --link-static`) when it is enabled. Other backends,
+    /// such as CUDA or ROCm, always link their runtimes dynamically.
+    pub prefer_static: bool,
+    /// Strip the symbols not needed for dynamic linking from the installed shared
+    /// libraries after the build, with `strip` or the tool named by `STRIP`.
+    pub strip: bool,
+    /// Make the installed libraries resolve their dependencies relative to their own
+    /// location (`$ORIGIN`, or `@loader_path` on Apple platforms) instead of the build
+    /// tree, so the install can be moved.
+    pub relocatable: bool,
+    /// The number of compile jobs to run in parallel, by default one per logical CPU
+    /// unless `limit_jobs_by_memory` is set.
+    pub jobs: Option<usize>,
+    /// When `jobs` is unset, run only as many compile jobs as fit in the available memory,
+    /// assuming about 2GB per job, to avoid the build being killed on memory-limited machines.
+    pub limit_jobs_by_memory: bool,
+    /// The maximum number of link steps to run in parallel, as linking TVM takes a lot of
+    /// memory. Only supported by the Ninja generator, ignored by the others.
+    pub link_jobs: Option<usize>,
+    /// Stop CMake's configure step, where dependencies are detected, if it runs for
+    /// longer than this. Probing a broken toolchain can otherwise hang indefinitely.
+    pub configure_timeout: Option<Duration>,
+    /// The CMake generator to build with, commonly `Ninja`, `Unix Makefiles` or on
+    /// Windows `Visual Studio 17 2022`. By default Ninja when `ninja` is on the `PATH`
+    /// and otherwise Unix Makefiles, or Visual Studio on Windows.
+    pub generator: Option<String>,
+    /// The toolset passed to Visual Studio generators, e.g. `v142`.
+    pub toolset: Option<String>,
+    /// The platform passed to Visual Studio generators, e.g. `x64`.
+    pub platform: Option<String>,
+    /// Don't contact the remote to check whether an existing checkout is behind it.
+    pub offline: bool,
+    /// The proxy to use for git operations, by default the proxy is picked up from
+    /// git's configuration and the `http_proxy`/`https_proxy`/`no_proxy` variables.
+    pub proxy: Option<String>,
+    /// The private SSH key to authenticate to SSH remotes with, by default the keys of
+    /// the running SSH agent are used. The key can't be protected by a passphrase.
+    ///
+    /// HTTPS remotes authenticate with the token in `GIT_TOKEN` when it is set, and
+    /// otherwise with git's configured credential helper.
+    pub ssh_key: Option<PathBuf>,
+    /// Write `env.sh`, and `env.bat` on Windows, into the revision directory after the
+    /// build, setting the environment variables needed to use the installation.
+    pub write_env: bool,
+    /// A command to run after a successful build. It runs with `TVM_HOME` set to the
+    /// source tree, `TVM_LIBRARY_PATH` to the installed libraries and
+    /// `TVM_INCLUDE_DIR` to the installed headers.
+    pub post_build: Option<std::process::Command>,
+    /// Additional CMake defines for options `settings` has no field for, applied after
+    /// all other defines. See `BuildConfig::set_option`.
+    pub extra_defines: Vec<(String, String)>,
+    /// Options, e.g. `USE_LLVM`, whose values aren't checked to be existing paths.
+    ///
+    /// Values of settings which are paths, rather than `ON`, `OFF` or a name CMake looks
+    /// up such as `llvm-config-15`, must otherwise exist.
+    pub unchecked_paths: Vec<String>,
+    /// A channel to report the progress of the build on.
+    pub progress: Option<Sender<BuildEvent>>,
     pub settings: UserSettings,
 }
 
-impl std::default::Default for BuildConfig {
-    fn default() -> BuildConfig {
-        BuildConfig {
-            repository: None,
-            repository_path: None,
-            output_path: None,
-            branch: None,
-            verbose: false,
-            clean: false,
-            settings: UserSettings::default(),
+/// Quote a value for use as an argument in a CMake script if required.
+fn cmake_quote(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || "\"();#\\".contains(c)) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn render_config_cmake(defines: impl Iterator<Item = (String, String)>) -> String {
+    defines
+        .map(|(key, value)| format!("set({} {})\n", key, cmake_quote(&value)))
+        .collect()
+}
+
+/// Read the entries of a `CMakeCache.txt`, dropping their types.
+fn read_cmake_cache(path: &Path) -> Result<BTreeMap<String, String>, Error> {
+    let contents = std::fs::read_to_string(path)?;
+    let entries = contents
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with("//"))
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            let key = key.split_once(':').map_or(key, |(key, _ty)| key);
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// Whether a CMake value is truthy, following the rules of CMake's `if()`.
+fn is_enabled(value: &str) -> bool {
+    let value = value.to_ascii_uppercase();
+    !(value.is_empty()
+        || value.ends_with("-NOTFOUND")
+        || ["0", "OFF", "NO", "FALSE", "N", "IGNORE", "NOTFOUND"].contains(&value.as_str()))
+}
+
+/// The defines which make the installed libraries find their dependencies relative
+/// to their own location.
+fn relocatable_defines(target: &Target) -> Vec<(String, String)> {
+    let origin = if target.is_apple() {
+        "@loader_path"
+    } else {
+        "$ORIGIN"
+    };
+
+    let mut defines = vec![
+        ("CMAKE_INSTALL_RPATH".to_string(), origin.to_string()),
+        ("CMAKE_BUILD_WITH_INSTALL_RPATH".to_string(), "ON".to_string()),
+    ];
+    if target.is_apple() {
+        defines.push(("CMAKE_INSTALL_NAME_DIR".to_string(), "@rpath".to_string()));
+    }
+    defines
+}
+
+/// Check a revision is a valid git ref name before it is used as one, and as a path.
+pub(crate) fn validate_revision(revision: &str) -> Result<(), Error> {
+    // Branches and tags share the ref name rules, which rule out `..`, leading dots
+    // and control characters among others. Like git we also rule out a leading dash,
+    // which the git CLI would take for an option.
+    if !revision.starts_with('-')
+        && git2::Reference::is_valid_name(&format!("refs/heads/{}", revision))
+    {
+        Ok(())
+    } else {
+        Err(Error::InvalidRevision(revision.to_string()))
+    }
+}
+
+/// Whether `revision` looks like a full or abbreviated commit SHA rather than the
+/// name of a branch or tag.
+pub(crate) fn is_commit_sha(revision: &str) -> bool {
+    (7..=40).contains(&revision.len()) && revision.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Which kind of ref a revision names.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RefKind {
+    Branch,
+    Tag,
+}
+
+/// The kind and short name of a revision naming a ref in full, as `refs/heads/<branch>`
+/// or `refs/tags/<tag>`, the way to give a branch or tag starting with a dash.
+fn qualified_ref(revision: &str) -> Option<(RefKind, &str)> {
+    if let Some(branch) = revision.strip_prefix("refs/heads/") {
+        Some((RefKind::Branch, branch))
+    } else {
+        revision.strip_prefix("refs/tags/").map(|tag| (RefKind::Tag, tag))
+    }
+}
+
+/// Escape a revision for use as a single path component, so that for example the
+/// branch `feature/x` doesn't create nested directories.
+fn escape_path_component(revision: &str) -> String {
+    revision
+        .chars()
+        .flat_map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '+' => vec![c],
+            c => format!("%{:02X}", c as u32).chars().collect(),
+        })
+        .collect()
+}
+
+/// The revision a path component produced by `escape_path_component` stands for.
+fn unescape_path_component(component: &str) -> String {
+    let mut bytes = vec![];
+    let mut rest = component.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(escaped) if byte == b'%' => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// The total size of the files under `path`, without following symbolic links.
+fn directory_size(path: &Path) -> Result<u64, Error> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        size += directory_size(&entry?.path())?;
+    }
+    Ok(size)
+}
+
+/// Extract a TVM source archive to `source_path`.
+fn extract_source_archive(archive: &Path, source_path: &Path) -> Result<(), Error> {
+    let invalid = |message: &str| Error::InvalidArchive {
+        archive: archive.to_path_buf(),
+        message: message.to_string(),
+    };
+
+    if !archive.exists() {
+        return Err(Error::FileNotFound(archive.display().to_string()));
+    }
+    let format = Format::from_path(archive)
+        .ok_or_else(|| invalid("unsupported format, expected .tar.gz, .tar.xz or .zip"))?;
+
+    info!(archive = %archive.display(), "extracting the source archive");
+    let staging = source_path.with_extension("extracting");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+    archive::extract(archive, format, &staging)?;
+
+    // Archives usually wrap the source in a single top level directory.
+    let entries = std::fs::read_dir(&staging)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let root = match entries.as_slice() {
+        [directory] if directory.is_dir() => directory.clone(),
+        _ => staging.clone(),
+    };
+
+    if !root.join("CMakeLists.txt").exists() || !root.join("include").join("tvm").exists() {
+        std::fs::remove_dir_all(&staging)?;
+        return Err(invalid("it does not contain TVM's source"));
+    }
+
+    std::fs::rename(&root, source_path)?;
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    Ok(())
+}
+
+/// Detach `repo`'s HEAD at `commit` and check it out, discarding local changes.
+fn checkout_commit(repo: &git2::Repository, commit: &str) -> Result<(), Error> {
+    repo.set_head_detached(git2::Oid::from_str(commit)?)?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .map_err(|e| checkout_error(commit, e))
+}
+
+/// Refuse to force a checkout of `revision` over uncommitted changes to the files
+/// `repo` tracks. Untracked files and submodules are left alone by the checkout.
+fn ensure_clean(repo: &git2::Repository, revision: &str) -> Result<(), Error> {
+    let path = repo.workdir().unwrap_or_else(|| repo.path());
+    if lfs::uses_lfs(path)? {
+        // libgit2 doesn't run LFS filters, so every fetched LFS object looks changed.
+        debug!(path = %path.display(), "not checking a working tree using LFS for changes");
+        return Ok(());
+    }
+
+    let mut options = git2::StatusOptions::new();
+    options
+        .include_untracked(false)
+        .include_ignored(false)
+        .exclude_submodules(true);
+    if repo.statuses(Some(&mut options))?.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::DirtyWorkingTree {
+            path: path.to_path_buf(),
+            revision: revision.to_string(),
+        })
+    }
+}
+
+/// Whether a git error occurred while updating the working tree, rather than while
+/// finding or fetching what to check out.
+fn is_checkout_error(e: &git2::Error) -> bool {
+    e.class() == git2::ErrorClass::Checkout
+        || matches!(
+            e.code(),
+            git2::ErrorCode::Conflict
+                | git2::ErrorCode::Uncommitted
+                | git2::ErrorCode::Locked
+                | git2::ErrorCode::Modified
+                | git2::ErrorCode::Unmerged
+        )
+}
+
+/// Report a failed checkout of `revision` as `Error::CheckoutFailed` when the working
+/// tree was the problem.
+fn checkout_error(revision: &str, e: git2::Error) -> Error {
+    if is_checkout_error(&e) {
+        Error::CheckoutFailed {
+            revision: revision.to_string(),
+            source: e,
+        }
+    } else {
+        e.into()
+    }
+}
+
+/// The commit a branch or tag named `revision` points at in the remote repository,
+/// like `git ls-remote`, `None` when there is no such ref or the remote is unreachable.
+fn remote_tip(
+    url: &str,
+    revision: &str,
+    callbacks: RemoteCallbacks,
+    proxy: ProxyOptions,
+) -> Option<git2::Oid> {
+    let mut remote = git2::Remote::create_detached(url).ok()?;
+    let connection = remote
+        .connect_auth(git2::Direction::Fetch, Some(callbacks), Some(proxy))
+        .ok()?;
+    let heads = connection.list().ok()?;
+
+    // Annotated tags are listed twice, prefer the peeled `^{}` entry naming the commit.
+    let candidates = if qualified_ref(revision).is_some() {
+        vec![format!("{}^{{}}", revision), revision.to_string()]
+    } else {
+        vec![
+            format!("refs/heads/{}", revision),
+            format!("refs/tags/{}^{{}}", revision),
+            format!("refs/tags/{}", revision),
+        ]
+    };
+    candidates.iter().find_map(|name| {
+        heads
+            .iter()
+            .find(|head| head.name() == name)
+            .map(|head| head.oid())
+    })
+}
+
+/// The credentials to answer a remote's request for `allowed` with, each kind is only
+/// tried once so that rejected credentials fail rather than being retried forever.
+fn credentials(
+    ssh_key: Option<&Path>,
+    url: &str,
+    username_from_url: Option<&str>,
+    allowed: CredentialType,
+    tried: &mut CredentialType,
+) -> Result<Cred, git2::Error> {
+    let kinds = [
+        CredentialType::USERNAME,
+        CredentialType::SSH_KEY,
+        CredentialType::USER_PASS_PLAINTEXT,
+        CredentialType::DEFAULT,
+    ];
+    let kind = kinds
+        .iter()
+        .copied()
+        .find(|kind| allowed.contains(*kind) && !tried.contains(*kind))
+        .ok_or_else(|| {
+            git2::Error::from_str(&format!(
+                "authenticating to {} failed, set `ssh_key`, add the key to the SSH agent or \
+                 set {} to an access token",
+                url, GIT_TOKEN_VAR
+            ))
+        })?;
+    tried.insert(kind);
+
+    let username = username_from_url.unwrap_or("git");
+    if kind == CredentialType::USERNAME {
+        Cred::username(username)
+    } else if kind == CredentialType::SSH_KEY {
+        match ssh_key {
+            Some(ssh_key) => Cred::ssh_key(username, None, ssh_key, None),
+            None => Cred::ssh_key_from_agent(username),
+        }
+    } else if kind == CredentialType::USER_PASS_PLAINTEXT {
+        match std::env::var(GIT_TOKEN_VAR) {
+            Ok(token) => Cred::userpass_plaintext(username_from_url.unwrap_or("git"), &token),
+            Err(_) => {
+                let config = git2::Config::open_default()?;
+                Cred::credential_helper(&config, url, username_from_url)
+            }
         }
+    } else {
+        Cred::default()
     }
 }
 
-// convert to lazy<T>?
-pub(crate) fn tvm_build_directory() -> PathBuf {
-    let home_dir = dirs::home_dir().expect("requires a home directory");
-    home_dir.join(format!(".tvm_build_{}", std::env::consts::ARCH))
+/// The directory revisions are built in by default, `TVM_BUILD_DIR` when it is set,
+/// otherwise a directory in the user's home or, without one, in the temporary directory.
+pub(crate) fn tvm_build_directory() -> Result<PathBuf, Error> {
+    Ok(build_directory(std::env::var_os("TVM_BUILD_DIR"), dirs::home_dir()))
+}
+
+/// The default build directory given the value of `TVM_BUILD_DIR` and the home directory.
+fn build_directory(build_dir_var: Option<OsString>, home_dir: Option<PathBuf>) -> PathBuf {
+    if let Some(build_dir) = build_dir_var.filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(build_dir);
+    }
+
+    let build_dir = format!(".tvm_build_{}", std::env::consts::ARCH);
+    match home_dir {
+        Some(home_dir) => home_dir.join(build_dir),
+        None => {
+            let temp_dir = std::env::temp_dir();
+            warn!(
+                path = %temp_dir.display(),
+                "no home directory, building in the temporary directory, set TVM_BUILD_DIR to \
+                 keep builds elsewhere"
+            );
+            temp_dir.join(build_dir)
+        }
+    }
 }
 
 impl BuildConfig {
     // TODO: split per revision
+    /// The revision this configuration builds, without fetching it.
+    pub fn revision(&self) -> Result<Revision, Error> {
+        let branch = self.branch.clone().unwrap_or(DEFAULT_BRANCH.into());
+        let revision = Revision::new(branch, self.output_path.clone())?
+            .with_build_layout(self.build_layout)
+            .with_repository(self.repository_url());
+        Ok(match &self.repository_path {
+            Some(path) => revision.with_source_path(PathBuf::from(path)),
+            None => revision,
+        })
+    }
+
+    /// The URL the repository is cloned from, normalized.
+    pub fn repository_url(&self) -> String {
+        let repository = self.repository.as_deref().unwrap_or(TVM_REPO).trim();
+        repository.trim_end_matches('/').to_string()
+    }
+
     pub fn get_revision(&self) -> Result<Revision, Error> {
         info!("tvm_build::build");
-        let repository_url = self.repository.clone().unwrap_or(TVM_REPO.into());
-
-        let branch = self.branch.clone().unwrap_or(DEFAULT_BRANCH.into());
-        let revision = Revision::new(branch, self.output_path.clone());
+        let repository_url = self.repository_url();
+        let revision = self.revision()?;
+        let revision_path = revision.path();
 
-        let revision_path = match &self.repository_path {
-            Some(path) => std::path::Path::new(&path).into(),
-            // todo: check that the provided path exists
-            None => revision.path(),
+        // Read the lock before cleaning, which removes it along with the build.
+        let locked_manifest = if self.locked {
+            let manifest = BuildManifest::read_lock(&revision)?.ok_or_else(|| {
+                Error::InvalidSettings(
+                    "there are no commits to lock to, run `tvm-build lock` first".into(),
+                )
+            })?;
+            Some(manifest)
+        } else {
+            None
         };
 
         // If a user specifies the repository directory we assume we
@@ -300,134 +1136,1022 @@ impl BuildConfig {
         if revision_path.exists() && self.clean && self.repository_path.is_none() {
             // This fails if doesn't exist
             std::fs::remove_dir_all(&revision_path)?;
+        } else if self.clean && revision.build_path().exists() {
+            // We still own the build directory, whatever the layout.
+            std::fs::remove_dir_all(revision.build_path())?;
+        }
+
+        if self.repository_path.is_some() {
+            let source_path = revision.source_path();
+            if !source_path.is_dir() {
+                return Err(Error::DirectoryNotFound(source_path.display().to_string()));
+            }
+            if !source_path.join("CMakeLists.txt").is_file() {
+                return Err(Error::NotSourceTree { path: source_path });
+            }
+            if !source_path.join(".git").exists() {
+                warn!(
+                    source = %source_path.display(),
+                    "the source tree isn't a git checkout so changes to it aren't detected, \
+                     build with --force after changing it"
+                );
+            }
+            info!(source = %source_path.display(), "building the existing source tree");
+            return Ok(revision);
+        }
+
+        if let Some(archive) = &self.source_archive {
+            if !revision.source_path().exists() {
+                extract_source_archive(archive, &revision.source_path())?;
+            }
+            return Ok(revision);
         }
 
         if !revision.source_path().exists() {
-            let mut repo_builder = RepoBuilder::new();
-            repo_builder.branch(&revision.revision);
-            println!("{}", repository_url);
+            info!(repository = %repository_url, revision = %revision.revision, "cloning");
             let repo_path = revision_path.join("source");
-            let repo = match repo_builder.clone(&repository_url, &repo_path) {
-                Ok(repo) => Ok(repo),
-                Err(e) => Err(match e.code() {
-                    git2::ErrorCode::NotFound => Error::RevisionNotFound {
-                        repository: repository_url,
-                        revision: revision.revision.clone(),
-                    },
-                    _ => e.into(),
-                }),
-            }?;
+            let shallow = match self.shallow_history() {
+                Some(history) => Some(self.shallow_clone(
+                    &repository_url,
+                    &revision.revision,
+                    &repo_path,
+                    &history,
+                )),
+                None if self.clone_depth.is_some() => Some(self.full_clone(
+                    &repository_url,
+                    &revision.revision,
+                    &repo_path,
+                    self.clone_depth,
+                )),
+                None => None,
+            };
+            let repo = match shallow {
+                Some(Err(e))
+                    if self.fallback_to_full && !matches!(e, Error::RepositoryNotFound { .. }) =>
+                {
+                    warn!(reason = %e, "the shallow clone failed, falling back to a full clone");
+                    if repo_path.exists() {
+                        std::fs::remove_dir_all(&repo_path)?;
+                    }
+                    self.full_clone(&repository_url, &revision.revision, &repo_path, None)?
+                }
+                Some(result) => result?,
+                None => self.full_clone(&repository_url, &revision.revision, &repo_path, None)?,
+            };
+            if is_commit_sha(&revision.revision) {
+                if let Err(e) = self.checkout_sha(&repo, &repository_url, &revision.revision) {
+                    // Don't leave a checkout of the wrong commit behind to be built later.
+                    std::fs::remove_dir_all(&repo_path)?;
+                    return Err(e);
+                }
+            }
             // todo(@jroesch): key build repos by sha? right now branch alone potentially conflicts.
-            let submodules = repo.submodules()?;
-            for mut submodule in submodules {
-                submodule.update(true, None)?;
+            // Locked submodules are checked out at their recorded commits below instead.
+            if locked_manifest.is_none() {
+                self.update_submodules(&repo)?;
+            }
+            lfs::checkout_objects(&repo_path)?;
+        } else if !self.offline
+            && locked_manifest.is_none()
+            && !is_commit_sha(&revision.revision)
+        {
+            if self.update {
+                self.update_checkout(&revision)?;
+            } else {
+                self.warn_if_behind_remote(&repository_url, &revision);
             }
         }
 
+        if !self.track_remote_submodules.is_empty() {
+            self.track_remote(&revision)?;
+        }
+
+        if let Some(manifest) = &locked_manifest {
+            self.checkout_locked(&revision, manifest)?;
+        }
+
         Ok(revision)
     }
 
-    fn setting_key_value<T: CMakeSettingValue>(k: &str, value: T) -> (String, String) {
-        (k.to_string(), value.as_string_value())
+    /// Update the submodules of a fresh or updated checkout to the commits `repo` points at.
+    fn update_submodules(&self, repo: &git2::Repository) -> Result<(), Error> {
+        self.update_nested_submodules(repo, Path::new(""))
     }
 
-    // Returns any user settings to be "set" as cmake definitions.
-    pub fn as_cmake_define_key_values(&self) -> impl Iterator<Item = (String, String)> {
-        let UserSettings {
-            use_cuda,
-            use_opencl,
-            use_vulkan,
-            use_metal,
-            use_rocm,
-            rocm_path,
-            use_hexagon_device,
-            use_heaxgon_dsk,
-            use_rpc,
-            use_threads,
-            use_llvm,
-            use_stackvm_runtime,
-            use_graph_runtime,
-            use_graph_runtime_debug,
-            use_openmp,
-            use_relay_debug,
-            use_rtti,
-            use_mscv_mt,
-            use_micro,
-            use_install_dev,
-            hide_private_symbols,
-            use_fallback_stl_map,
-            use_ethosn,
-            use_index_default_i64,
-            use_tf_tvmdsoop,
-            use_byodt_posit,
-            use_blas,
-            use_mkl,
-            use_dnnl,
-            use_cudnn,
-            use_cublas,
-            use_thrust,
-            use_miopen,
-            use_rocblas,
-            use_sort,
-            use_nnpack,
-            use_random,
-            use_micro_standalone_runtime,
-            use_cpp_rpc,
-            use_tflite,
-            use_tensorflow_path,
-            use_coreml,
-            use_target_onnx,
-            use_arm_compute_lib,
-            use_arm_compute_lib_graph_runtime,
-            use_tensorrt_codegen,
-            use_tensorrt_runtime,
-            use_rust_ext,
-            use_vitis_ai,
-            build_static_runtime
-        } = &self.settings;
+    /// Update the submodules of `repo`, which is at `prefix` in the source tree, and
+    /// the submodules nested in them in turn, like `git submodule update --recursive`.
+    fn update_nested_submodules(
+        &self,
+        repo: &git2::Repository,
+        prefix: &Path,
+    ) -> Result<(), Error> {
+        let repo_path = repo.workdir().unwrap_or_else(|| repo.path()).to_path_buf();
+        let submodules = repo.submodules()?;
+        let total = submodules.len();
+        for (index, mut submodule) in submodules.into_iter().enumerate() {
+            let path = prefix.join(submodule.path());
+            self.update_submodule(&mut submodule, &path, index + 1, total)?;
+            lfs::checkout_objects(&repo_path.join(submodule.path()))?;
+            self.update_nested_submodules(&submodule.open()?, &path)?;
+        }
+        Ok(())
+    }
 
-        vec![
-            use_cuda
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_CUDA", s)),
-            use_opencl
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_OPENCL", s)),
-            use_vulkan
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_VULKAN", s)),
-            use_metal
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_METAL", s)),
-            use_rocm
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_ROCM", s)),
-            rocm_path
-                .as_ref()
-                .map(|s| Self::setting_key_value("ROCM_PATH", s)),
-            use_hexagon_device
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_HEXAGON_DEVICE", s)),
-            use_heaxgon_dsk
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_HEAXGON_DSK", s)),
-            use_rpc
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_RPC", s)),
-            use_threads
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_THREADS", s)),
-            use_llvm
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_LLVM", s)),
-            use_stackvm_runtime
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_STACKVM_RUNTIME", s)),
-            use_graph_runtime
-                .as_ref()
-                .map(|s| Self::setting_key_value("USE_GRAPH_RUNTIME", s)),
-            use_graph_runtime_debug
-                .as_ref()
+    /// Fetch the revision's branch and reset the existing checkout to its tip, the
+    /// equivalent of `git fetch` and `git reset --hard origin/<branch>`.
+    ///
+    /// A revision which isn't a branch of the remote, such as a tag, is left as it is.
+    fn update_checkout(&self, revision: &Revision) -> Result<(), Error> {
+        let branch = match qualified_ref(&revision.revision) {
+            Some((RefKind::Branch, branch)) => branch,
+            Some((RefKind::Tag, _)) => {
+                debug!(revision = %revision.revision, "a tag, leaving it as it is");
+                return Ok(());
+            }
+            None => &revision.revision,
+        };
+        let repo = git2::Repository::open(revision.source_path())?;
+        let refspec = format!("+refs/heads/{0}:refs/remotes/origin/{0}", branch);
+        info!(revision = %branch, "fetching the latest commits");
+        let mut fetch_options = self.fetch_options();
+        if let (Some(depth), true) = (self.clone_depth, repo.is_shallow()) {
+            fetch_options.depth(depth as i32);
+        }
+        repo.find_remote("origin")?
+            .fetch(&[&refspec], Some(&mut fetch_options), None)?;
+
+        let tip = match repo.find_reference(&format!("refs/remotes/origin/{}", branch)) {
+            Ok(reference) => reference.peel_to_commit()?,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                debug!(revision = %branch, "not a branch of the remote, leaving it as it is");
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let head = repo.head()?.peel_to_commit()?;
+        if head.id() == tip.id() {
+            debug!("the checkout is up to date");
+            return Ok(());
+        }
+
+        ensure_clean(&repo, branch)?;
+        info!(from = %head.id(), to = %tip.id(), "updating the checkout");
+        repo.reset(
+            tip.as_object(),
+            git2::ResetType::Hard,
+            Some(git2::build::CheckoutBuilder::new().force()),
+        )
+        .map_err(|e| checkout_error(branch, e))?;
+        self.update_submodules(&repo)?;
+        lfs::checkout_objects(&revision.source_path())?;
+        Ok(())
+    }
+
+    /// Warn when the remote's tip of a branch or tag differs from the existing checkout,
+    /// which would otherwise silently be built as is.
+    fn warn_if_behind_remote(&self, repository_url: &str, revision: &Revision) {
+        let local = git2::Repository::open(revision.source_path())
+            .and_then(|repo| Ok(repo.head()?.peel_to_commit()?.id()));
+        let local = match local {
+            Ok(local) => local,
+            Err(e) => {
+                debug!(%e, "couldn't read the checked out commit");
+                return;
+            }
+        };
+
+        let remote = remote_tip(
+            repository_url,
+            &revision.revision,
+            self.remote_callbacks(),
+            self.proxy_options(),
+        );
+        match remote {
+            Some(remote) if remote != local => warn!(
+                revision = %revision.revision,
+                %local,
+                %remote,
+                "the revision has moved on since it was fetched, the old source is built, \
+                 rebuild with --update or --clean to build the latest"
+            ),
+            Some(_) => debug!("the checkout matches the remote"),
+            None => debug!("the revision isn't a branch or tag of the remote, or it is unreachable"),
+        }
+    }
+
+    /// The `git clone` argument limiting the history fetched, if any.
+    fn shallow_history(&self) -> Option<String> {
+        match (&self.shallow_since, &self.shallow_since_tag) {
+            (Some(date), _) => Some(format!("--shallow-since={}", date)),
+            (None, Some(tag)) => Some(format!("--shallow-exclude={}", tag)),
+            (None, None) => None,
+        }
+    }
+
+    /// Clone `revision` with libgit2, reporting progress. The history is limited to
+    /// `depth` commits when set.
+    ///
+    /// A revision which isn't a branch is looked up as a tag, after cloning the default
+    /// branch, as libgit2 can only clone branches directly. A revision naming its ref in
+    /// full is only looked up as that kind of ref.
+    fn full_clone(
+        &self,
+        repository_url: &str,
+        revision: &str,
+        repo_path: &Path,
+        depth: Option<u32>,
+    ) -> Result<git2::Repository, Error> {
+        // Commits and tags are checked out after cloning the default branch.
+        let (branch, tag) = match qualified_ref(revision) {
+            Some((RefKind::Branch, branch)) => (Some(branch), None),
+            Some((RefKind::Tag, tag)) => (None, Some(tag)),
+            None if is_commit_sha(revision) => (None, None),
+            None => (Some(revision), Some(revision)),
+        };
+        let revision_not_found = || Error::RevisionNotFound {
+            repository: repository_url.to_string(),
+            revision: revision.to_string(),
+        };
+        let clone_tag = |tag| {
+            if repo_path.exists() {
+                std::fs::remove_dir_all(repo_path)?;
+            }
+            let repo = self
+                .clone_with_progress(repository_url, None, repo_path, depth)
+                .map_err(|e| checkout_error(revision, e))?;
+            let checkout = self.checkout_tag(&repo, tag);
+            if !matches!(checkout, Ok(true)) {
+                std::fs::remove_dir_all(repo_path)?;
+            }
+            match checkout {
+                Ok(true) => Ok(repo),
+                Ok(false) => Err(revision_not_found()),
+                Err(e) => Err(e),
+            }
+        };
+        if let (None, Some(tag)) = (branch, tag) {
+            return clone_tag(tag);
+        }
+
+        match self.clone_with_progress(repository_url, branch, repo_path, depth) {
+            Ok(repo) => Ok(repo),
+            // A missing revision and a missing repository can both surface as `NotFound`
+            // (or, for some hosts, as an authentication error) so check whether the
+            // repository itself is reachable to tell them apart.
+            Err(_) if !self.repository_exists(repository_url) => Err(Error::RepositoryNotFound {
+                repository: repository_url.to_string(),
+            }),
+            Err(e) if e.code() == git2::ErrorCode::NotFound && tag.is_some() => {
+                debug!(revision, "there is no branch of that name, looking for a tag");
+                clone_tag(revision)
+            }
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Err(revision_not_found()),
+            Err(e) => Err(checkout_error(revision, e)),
+        }
+    }
+
+    /// Clone `branch`, or the default branch, with `depth` commits of history or all of
+    /// it, reporting the objects received and logging every tenth of the way.
+    fn clone_with_progress(
+        &self,
+        repository_url: &str,
+        branch: Option<&str>,
+        repo_path: &Path,
+        depth: Option<u32>,
+    ) -> Result<git2::Repository, git2::Error> {
+        let mut last_percent = None;
+        let mut callbacks = self.remote_callbacks();
+        callbacks.transfer_progress(|stats| {
+            let percent = stats.received_objects() * 100 / stats.total_objects().max(1);
+            if last_percent != Some(percent) {
+                if last_percent.is_none_or(|last| last / 10 != percent / 10) {
+                    info!(
+                        received_objects = stats.received_objects(),
+                        total_objects = stats.total_objects(),
+                        received_bytes = stats.received_bytes(),
+                        "cloning, {}% done",
+                        percent
+                    );
+                }
+                last_percent = Some(percent);
+                self.report(BuildEvent::Cloning {
+                    received_objects: stats.received_objects(),
+                    total_objects: stats.total_objects(),
+                    received_bytes: stats.received_bytes(),
+                });
+            }
+            true
+        });
+
+        let mut fetch_options = FetchOptions::new();
+        fetch_options
+            .remote_callbacks(callbacks)
+            .proxy_options(self.proxy_options());
+        if let Some(depth) = depth {
+            fetch_options.depth(depth as i32);
+        }
+
+        let mut repo_builder = RepoBuilder::new();
+        if let Some(branch) = branch {
+            repo_builder.branch(branch);
+        }
+        repo_builder.fetch_options(fetch_options);
+        repo_builder.clone(repository_url, repo_path)
+    }
+
+    /// Check out the commit the tag `tag` points at, fetching the tag if the clone
+    /// didn't. Returns whether the remote has the tag.
+    fn checkout_tag(&self, repo: &git2::Repository, tag: &str) -> Result<bool, Error> {
+        let name = format!("refs/tags/{}", tag);
+        if repo.find_reference(&name).is_err() {
+            let refspec = format!("+{0}:{0}", name);
+            if let Err(e) = repo
+                .find_remote("origin")?
+                .fetch(&[refspec], Some(&mut self.fetch_options()), None)
+            {
+                debug!(%e, "fetching the tag failed");
+            }
+        }
+
+        let commit = match repo.find_reference(&name).and_then(|tag| tag.peel_to_commit()) {
+            Ok(commit) => commit,
+            Err(_) => return Ok(false),
+        };
+        info!(tag, commit = %commit.id(), "checking out the tag");
+        checkout_commit(repo, &commit.id().to_string())?;
+        Ok(true)
+    }
+
+    /// Clone with limited history using the git CLI, libgit2 only supports limiting
+    /// the history by depth.
+    fn shallow_clone(
+        &self,
+        repository_url: &str,
+        revision: &str,
+        repo_path: &Path,
+        history: &str,
+    ) -> Result<git2::Repository, Error> {
+        let mut cmd = std::process::Command::new("git");
+        if let Some(proxy) = &self.proxy {
+            cmd.arg("-c").arg(format!("http.proxy={}", proxy));
+        }
+        cmd.arg("clone").arg(history);
+        if !is_commit_sha(revision) {
+            // `--branch` takes the short name of a branch or tag, attached so that a name
+            // starting with a dash isn't taken for an option.
+            let name = qualified_ref(revision).map_or(revision, |(_, name)| name);
+            cmd.arg(format!("--branch={}", name));
+        }
+        cmd.arg("--")
+            .arg(repository_url)
+            .arg(repo_path);
+
+        debug!(command = ?cmd, "running");
+        let output = cmd.output()?;
+        if !output.status.success() {
+            return Err(if !self.repository_exists(repository_url) {
+                Error::RepositoryNotFound {
+                    repository: repository_url.to_string(),
+                }
+            } else {
+                Error::GitFailed {
+                    command: format!("{:?}", cmd),
+                    message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                }
+            });
+        }
+
+        Ok(git2::Repository::open(repo_path)?)
+    }
+
+    /// Check out the commit `sha`, fetching it when it isn't reachable from the
+    /// branches which were cloned. Only full SHAs can be fetched.
+    ///
+    /// A commit outside the history of a shallow clone is an error, unless
+    /// `fallback_to_full` is set and the full history is fetched to find it.
+    fn checkout_sha(
+        &self,
+        repo: &git2::Repository,
+        repository_url: &str,
+        sha: &str,
+    ) -> Result<(), Error> {
+        if repo.revparse_single(sha).is_err() && sha.len() == 40 {
+            debug!(commit = sha, "the commit isn't on a branch, fetching it");
+            if let Err(e) = repo
+                .find_remote("origin")?
+                .fetch(&[sha], Some(&mut self.fetch_options()), None)
+            {
+                debug!(%e, "fetching the commit failed");
+            }
+        }
+
+        if repo.revparse_single(sha).is_err() && repo.is_shallow() {
+            if !self.fallback_to_full {
+                return Err(Error::OutsideShallowHistory {
+                    commit: sha.to_string(),
+                });
+            }
+            warn!(
+                commit = sha,
+                "the commit is outside the shallow history, fetching the full history"
+            );
+            self.unshallow(repo.workdir().unwrap_or_else(|| repo.path()))?;
+        }
+
+        let commit = repo
+            .revparse_single(sha)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|_| Error::RevisionNotFound {
+                repository: repository_url.to_string(),
+                revision: sha.to_string(),
+            })?;
+        info!(commit = %commit.id(), "checking out the commit");
+        checkout_commit(repo, &commit.id().to_string())
+    }
+
+    /// Fetch the history a shallow clone left out using the git CLI.
+    fn unshallow(&self, source_path: &Path) -> Result<(), Error> {
+        let mut cmd = std::process::Command::new("git");
+        if let Some(proxy) = &self.proxy {
+            cmd.arg("-c").arg(format!("http.proxy={}", proxy));
+        }
+        cmd.arg("-C")
+            .arg(source_path)
+            .arg("fetch")
+            .arg("--unshallow")
+            .arg("--tags");
+
+        debug!(command = ?cmd, "running");
+        let output = cmd.output()?;
+        if !output.status.success() {
+            return Err(Error::GitFailed {
+                command: format!("{:?}", cmd),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Update the allowlisted submodules to the tip of their remote branch using the git
+    /// CLI, libgit2 has no equivalent of `git submodule update --remote`.
+    fn track_remote(&self, revision: &Revision) -> Result<(), Error> {
+        let source_path = revision.source_path();
+        let repo = git2::Repository::open(&source_path)?;
+        let total = self.track_remote_submodules.len();
+        for (index, name) in self.track_remote_submodules.iter().enumerate() {
+            let submodule = repo
+                .submodules()?
+                .into_iter()
+                .find(|submodule| {
+                    submodule.name() == Some(name.as_str())
+                        || submodule.path() == Path::new(name)
+                })
+                .ok_or_else(|| {
+                    Error::InvalidSettings(format!("there is no submodule named `{}`", name))
+                })?;
+
+            warn!(submodule = %name, "tracking the remote branch, the build isn't reproducible");
+            self.report(BuildEvent::UpdatingSubmodule {
+                name: name.clone(),
+                index: index + 1,
+                total,
+            });
+
+            let mut cmd = std::process::Command::new("git");
+            if let Some(proxy) = &self.proxy {
+                cmd.arg("-c").arg(format!("http.proxy={}", proxy));
+            }
+            cmd.current_dir(&source_path)
+                .args(["submodule", "update", "--init", "--remote", "--"])
+                .arg(submodule.path());
+
+            debug!(command = ?cmd, "running");
+            let output = cmd.output()?;
+            if !output.status.success() {
+                return Err(Error::GitFailed {
+                    command: format!("{:?}", cmd),
+                    message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                });
+            }
+            lfs::checkout_objects(&source_path.join(submodule.path()))?;
+        }
+        Ok(())
+    }
+
+    /// Update the `index`th of `total` submodules, reporting the objects fetched for it.
+    fn update_submodule(
+        &self,
+        submodule: &mut git2::Submodule,
+        path: &Path,
+        index: usize,
+        total: usize,
+    ) -> Result<(), Error> {
+        let name = path.display().to_string();
+        debug!(submodule = %name, index, total, "updating submodule");
+        self.report(BuildEvent::UpdatingSubmodule {
+            name: name.clone(),
+            index,
+            total,
+        });
+
+        if let (Some(depth), Err(_)) = (self.clone_depth, submodule.open()) {
+            match self.shallow_clone_submodule(submodule, depth) {
+                Ok(()) => return Ok(()),
+                // Not every server lets a commit be fetched by its SHA.
+                Err(e) => {
+                    debug!(submodule = %name, %e, "the shallow clone failed, cloning in full")
+                }
+            }
+        }
+
+        let mut last_percent = None;
+        let mut callbacks = self.remote_callbacks();
+        callbacks.transfer_progress(|stats| {
+            let percent = stats.received_objects() * 100 / stats.total_objects().max(1);
+            if last_percent != Some(percent) {
+                last_percent = Some(percent);
+                self.report(BuildEvent::FetchingSubmodule {
+                    name: name.clone(),
+                    received_objects: stats.received_objects(),
+                    total_objects: stats.total_objects(),
+                });
+            }
+            true
+        });
+
+        let mut fetch_options = self.fetch_options();
+        fetch_options.remote_callbacks(callbacks);
+        let mut update_options = SubmoduleUpdateOptions::new();
+        update_options.fetch(fetch_options);
+        submodule
+            .update(true, Some(&mut update_options))
+            .map_err(|e| checkout_error(&format!("the submodule `{}`", name), e))
+    }
+
+    /// Clone only the commit `submodule` is pinned at, with `depth` commits of history,
+    /// like `git submodule update --depth`.
+    fn shallow_clone_submodule(
+        &self,
+        submodule: &mut git2::Submodule,
+        depth: u32,
+    ) -> Result<(), Error> {
+        let commit = submodule
+            .index_id()
+            .or_else(|| submodule.head_id())
+            .ok_or_else(|| git2::Error::from_str("the submodule isn't pinned to a commit"))?
+            .to_string();
+        submodule.init(false)?;
+        let submodule_repo = submodule.repo_init(true)?;
+        let mut fetch_options = self.fetch_options();
+        fetch_options.depth(depth as i32);
+        submodule_repo
+            .find_remote("origin")?
+            .fetch(&[&commit], Some(&mut fetch_options), None)?;
+        checkout_commit(&submodule_repo, &commit)
+    }
+
+    /// Check out the source and submodule commits recorded in `manifest`.
+    fn checkout_locked(&self, revision: &Revision, manifest: &BuildManifest) -> Result<(), Error> {
+        info!(commit = %manifest.source_head, "checking out the locked commits");
+        let repo = git2::Repository::open(revision.source_path())?;
+        let source_head = git2::Oid::from_str(&manifest.source_head)?;
+        if repo.is_shallow() && repo.find_commit(source_head).is_err() {
+            if !self.fallback_to_full {
+                return Err(Error::OutsideShallowHistory {
+                    commit: manifest.source_head.clone(),
+                });
+            }
+            warn!(
+                commit = %manifest.source_head,
+                "the locked commit is outside the shallow history, fetching the full history"
+            );
+            self.unshallow(&revision.source_path())?;
+        }
+        ensure_clean(&repo, &manifest.source_head)?;
+        checkout_commit(&repo, &manifest.source_head)?;
+
+        let total = manifest.submodules.len();
+        for (index, (path, commit)) in manifest.submodules.iter().enumerate() {
+            let mut submodule = repo.find_submodule(path)?;
+            if submodule.open().is_err() {
+                self.update_submodule(&mut submodule, Path::new(path), index + 1, total)?;
+            }
+
+            let submodule_repo = submodule.open()?;
+            if submodule_repo.find_commit(git2::Oid::from_str(commit)?).is_err() {
+                // The commit may not be reachable from what the superproject points at.
+                submodule_repo
+                    .find_remote("origin")?
+                    .fetch(&[commit], Some(&mut self.fetch_options()), None)?;
+            }
+            ensure_clean(&submodule_repo, commit)?;
+            checkout_commit(&submodule_repo, commit)?;
+            lfs::checkout_objects(&revision.source_path().join(path))?;
+            self.update_nested_submodules(&submodule_repo, Path::new(path))?;
+        }
+
+        Ok(())
+    }
+
+    /// The libraries a build of `revision` with this configuration produces which
+    /// exist, only the runtime library for a runtime only build.
+    pub(crate) fn built_libraries(&self, revision: &Revision) -> Result<Vec<PathBuf>, Error> {
+        let libraries = revision.library_paths()?;
+        if self.runtime_only {
+            Ok(libraries.into_iter().filter(|path| is_runtime_library(path)).collect())
+        } else {
+            Ok(libraries)
+        }
+    }
+
+    /// The fetch options for updating submodules and fetching locked commits.
+    fn fetch_options(&self) -> FetchOptions<'_> {
+        let mut fetch_options = FetchOptions::new();
+        fetch_options
+            .remote_callbacks(self.remote_callbacks())
+            .proxy_options(self.proxy_options());
+        fetch_options
+    }
+
+    /// Check whether a remote repository can be connected to.
+    fn repository_exists(&self, url: &str) -> bool {
+        git2::Remote::create_detached(url)
+            .and_then(|mut remote| {
+                remote
+                    .connect_auth(
+                        git2::Direction::Fetch,
+                        Some(self.remote_callbacks()),
+                        Some(self.proxy_options()),
+                    )
+                    .map(|_| ())
+            })
+            .is_ok()
+    }
+
+    /// The callbacks for git operations, providing credentials for private remotes,
+    /// see `BuildConfig::ssh_key`.
+    fn remote_callbacks(&self) -> RemoteCallbacks<'_> {
+        let mut tried = CredentialType::empty();
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(move |url, username_from_url, allowed| {
+            credentials(self.ssh_key.as_deref(), url, username_from_url, allowed, &mut tried)
+        });
+        callbacks
+    }
+
+    /// Set the option `name`, e.g. `USE_CUDA`, to `value` without depending on the fields
+    /// of `UserSettings`.
+    ///
+    /// The value is parsed like a `CMakeSetting`. Options `UserSettings` has a field for
+    /// are set through it, any other option is added to `extra_defines` as is.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        let is_valid_name = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_valid_name {
+            return Err(Error::InvalidSettings(format!(
+                "`{}` is not a valid option name",
+                name
+            )));
+        }
+
+        if !self.settings.set(&name.to_ascii_lowercase(), value)? {
+            let setting: CMakeSetting = value.parse().unwrap();
+            self.extra_defines
+                .push((name.to_string(), (&setting).as_string_value()));
+        }
+        Ok(())
+    }
+
+    /// The CMake generator to build with, see `BuildConfig::generator`.
+    pub fn generator(&self) -> String {
+        match &self.generator {
+            Some(generator) => generator.clone(),
+            None => detect::default_generator().to_string(),
+        }
+    }
+
+    /// The number of compile jobs to run.
+    fn jobs(&self) -> usize {
+        match self.jobs {
+            Some(jobs) => jobs,
+            None if self.limit_jobs_by_memory => detect::jobs_for_memory(),
+            None => std::thread::available_parallelism().map_or(1, |cpus| cpus.get()),
+        }
+    }
+
+    /// The names of the steps requested to run after the build, as recorded in the
+    /// manifest.
+    pub(crate) fn post_build_steps(&self) -> BTreeSet<String> {
+        [
+            ("cpp_tests", self.run_cpp_tests),
+            ("docs", self.build_docs),
+            ("dependency_graph", self.dependency_graph),
+            ("strip", self.strip),
+        ]
+        .iter()
+        .filter(|(_, requested)| *requested)
+        .map(|(step, _)| step.to_string())
+        .collect()
+    }
+
+    /// The manifest a build of `revision` configured with `defines` records.
+    pub(crate) fn manifest_for(
+        &self,
+        revision: &Revision,
+        defines: Vec<(String, String)>,
+    ) -> Result<BuildManifest, Error> {
+        let config_cmake = match &self.config_cmake {
+            Some(path) if !path.exists() => {
+                return Err(Error::FileNotFound(path.display().to_string()))
+            }
+            Some(path) => Some(checksum::sha256_file(path)?),
+            None => None,
+        };
+
+        Ok(revision
+            .manifest(defines)?
+            .with_generator(self.generator())
+            .with_profile(self.profile)
+            .with_runtime_only(self.runtime_only)
+            .with_config_cmake(config_cmake)
+            .with_post_build_steps(self.post_build_steps()))
+    }
+
+    /// Strip the installed libraries if requested.
+    fn strip(&self, revision: &Revision) -> Result<Vec<StrippedLibrary>, Error> {
+        if !self.strip {
+            return Ok(vec![]);
+        }
+        strip::strip_libraries(&revision.library_paths()?)
+    }
+
+    /// Write the dependency graph if requested, returning its path.
+    fn dependency_graph(
+        &self,
+        cmake_config: &cmake::Config,
+        revision: &Revision,
+    ) -> Result<Option<PathBuf>, Error> {
+        if !self.dependency_graph {
+            return Ok(None);
+        }
+
+        let path = revision.path().join(DEPENDENCY_GRAPH);
+        cmake_config.dependency_graph(&path)?;
+        info!(path = %path.display(), "wrote the dependency graph");
+        Ok(Some(path))
+    }
+
+    /// Build the C++ API documentation if requested, returning the directory of the
+    /// generated HTML.
+    ///
+    /// TVM drives doxygen from its Makefile rather than CMake, so we run doxygen with
+    /// TVM's `Doxyfile` ourselves, writing the output to the build directory.
+    fn docs(&self, revision: &Revision) -> Result<Option<PathBuf>, Error> {
+        if !self.build_docs {
+            return Ok(None);
+        }
+
+        let source_path = revision.source_path();
+        let doxyfile = source_path.join("docs").join("Doxyfile");
+        let mut config = std::fs::read_to_string(&doxyfile)
+            .map_err(|_| Error::FileNotFound(doxyfile.display().to_string()))?;
+        let output_path = revision.build_path().join("docs");
+        std::fs::create_dir_all(&output_path)?;
+        config.push_str(&format!("\nOUTPUT_DIRECTORY = \"{}\"\n", output_path.display()));
+
+        info!(output = %output_path.display(), "building the documentation");
+        let mut child = std::process::Command::new("doxygen")
+            .arg("-")
+            .current_dir(&source_path)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(config.as_bytes())?;
+        let output = child.wait_with_output()?;
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            debug!(target: "doxygen", "{}", line);
+        }
+
+        if output.status.success() {
+            Ok(Some(output_path.join("html")))
+        } else {
+            Err(Error::DocsFailed {
+                status: output.status,
+                output: String::from_utf8_lossy(&output.stderr).into_owned(),
+            })
+        }
+    }
+
+    /// Build and run the C++ unit tests if requested, returning their summary.
+    fn cpp_tests(&self, cmake_config: &cmake::Config) -> Result<Option<TestSummary>, Error> {
+        if !self.run_cpp_tests {
+            return Ok(None);
+        }
+
+        info!("building the C++ tests");
+        cmake_config.build_target("cpptest", |line| info!(target: "cmake", "{}", line))?;
+
+        info!("running the C++ tests");
+        let mut summary = TestSummary::default();
+        let result = cmake_config.test(|line| {
+            info!(target: "ctest", "{}", line);
+            summary.record(line);
+        });
+
+        match result {
+            Ok(()) => Ok(Some(summary)),
+            // CTest exits unsuccessfully when any test fails.
+            Err(Error::CMakeFailed { .. }) if summary.failed > 0 => Err(Error::TestsFailed {
+                failed: summary.failed,
+            }),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The proxy settings for git operations.
+    fn proxy_options(&self) -> ProxyOptions<'_> {
+        let mut proxy_options = ProxyOptions::new();
+        match &self.proxy {
+            Some(proxy) => proxy_options.url(proxy),
+            None => proxy_options.auto(),
+        };
+        proxy_options
+    }
+
+    /// Report a progress event, if anyone is listening.
+    pub(crate) fn report(&self, event: BuildEvent) {
+        if let Some(progress) = &self.progress {
+            // The receiver going away just means nobody is interested anymore.
+            let _ = progress.send(event);
+        }
+    }
+
+    /// Render the settings in the format of TVM's `config.cmake`, one `set(...)`
+    /// per enabled setting.
+    pub fn to_config_cmake(&self) -> String {
+        render_config_cmake(self.as_cmake_define_key_values())
+    }
+
+    /// Copy the user supplied `config.cmake` into `binary_dir`, layering the
+    /// settings on top of it.
+    fn write_config_cmake(
+        config_cmake: &Path,
+        binary_dir: &Path,
+        defines: &[(String, String)],
+    ) -> Result<(), Error> {
+        if !config_cmake.exists() {
+            return Err(Error::FileNotFound(config_cmake.display().to_string()));
+        }
+
+        let mut contents = std::fs::read_to_string(config_cmake)?;
+        if !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+
+        contents.push_str("\n# Settings provided by tvm-build, these override the values above.\n");
+        contents.push_str(&render_config_cmake(defines.iter().cloned()));
+
+        std::fs::create_dir_all(binary_dir)?;
+        std::fs::write(binary_dir.join("config.cmake"), contents)?;
+        Ok(())
+    }
+
+    fn setting_key_value<T: CMakeSettingValue>(k: &str, value: T) -> (String, String) {
+        (k.to_string(), value.as_string_value())
+    }
+
+    /// The settings combined with those of the runtime and preset, if any.
+    fn effective_settings(&self) -> UserSettings {
+        let settings = match &self.runtime {
+            Some(runtime) => self.settings.clone().or(runtime.settings()),
+            None => self.settings.clone(),
+        };
+        match &self.preset {
+            Some(preset) => settings.or(preset.settings()),
+            None => settings,
+        }
+    }
+
+    // Returns any user settings to be "set" as cmake definitions.
+    pub fn as_cmake_define_key_values(&self) -> impl Iterator<Item = (String, String)> {
+        let UserSettings {
+            use_cuda,
+            use_opencl,
+            use_vulkan,
+            use_metal,
+            use_rocm,
+            rocm_path,
+            use_hexagon_device,
+            use_hexagon_sdk,
+            hexagon_toolchain,
+            use_rpc,
+            use_threads,
+            use_llvm,
+            use_stackvm_runtime,
+            use_graph_runtime,
+            use_graph_runtime_debug,
+            use_openmp,
+            use_relay_debug,
+            use_rtti,
+            use_mscv_mt,
+            use_micro,
+            use_install_dev,
+            hide_private_symbols,
+            use_fallback_stl_map,
+            use_ethosn,
+            use_index_default_i64,
+            use_tf_tvmdsoop,
+            use_byodt_posit,
+            use_blas,
+            use_mkl,
+            use_dnnl,
+            use_cudnn,
+            use_cublas,
+            use_thrust,
+            use_miopen,
+            use_rocblas,
+            use_sort,
+            use_nnpack,
+            use_random,
+            use_micro_standalone_runtime,
+            use_cpp_rpc,
+            use_tflite,
+            use_tensorflow_path,
+            use_coreml,
+            use_target_onnx,
+            use_arm_compute_lib,
+            use_arm_compute_lib_graph_runtime,
+            use_tensorrt_codegen,
+            use_tensorrt_runtime,
+            use_rust_ext,
+            use_vitis_ai,
+            use_gtest,
+            use_cutlass,
+            use_flashinfer,
+            dlpack_path,
+            dmlc_path,
+            rang_path,
+            compiler_rt_path,
+            picojson_path,
+            build_static_runtime
+        } = &self.effective_settings();
+
+        vec![
+            use_cuda
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_CUDA", s)),
+            use_opencl
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_OPENCL", s)),
+            use_vulkan
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_VULKAN", &detect::resolve_vulkan(s))),
+            use_metal
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_METAL", s)),
+            use_rocm
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_ROCM", s)),
+            rocm_path
+                .as_ref()
+                .map(|s| Self::setting_key_value("ROCM_PATH", s)),
+            use_hexagon_device
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_HEXAGON_DEVICE", s)),
+            use_hexagon_sdk
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_HEXAGON_SDK", s)),
+            hexagon_toolchain
+                .clone()
+                .or_else(|| use_hexagon_sdk.as_deref().and_then(detect::hexagon_toolchain))
+                .map(|s| Self::setting_key_value("HEXAGON_TOOLCHAIN", &s)),
+            use_rpc
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_RPC", s)),
+            use_threads
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_THREADS", s)),
+            use_llvm
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_LLVM", &self.llvm_setting(s))),
+            use_stackvm_runtime
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_STACKVM_RUNTIME", s)),
+            use_graph_runtime
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_GRAPH_RUNTIME", s)),
+            use_graph_runtime_debug
+                .as_ref()
                 .map(|s| Self::setting_key_value("USE_GRAPH_RUNTIME_DEBUG", s)),
             use_openmp
                 .as_ref()
@@ -534,45 +2258,911 @@ impl BuildConfig {
             use_vitis_ai
                 .as_ref()
                 .map(|s| Self::setting_key_value("USE_VITIS_AI", s)),
+            use_gtest
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_GTEST", s)),
+            use_cutlass
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_CUTLASS", s)),
+            use_flashinfer
+                .as_ref()
+                .map(|s| Self::setting_key_value("USE_FLASHINFER", s)),
+            dlpack_path
+                .as_ref()
+                .map(|s| Self::setting_key_value("DLPACK_PATH", s)),
+            dmlc_path
+                .as_ref()
+                .map(|s| Self::setting_key_value("DMLC_PATH", s)),
+            rang_path
+                .as_ref()
+                .map(|s| Self::setting_key_value("RANG_PATH", s)),
+            compiler_rt_path
+                .as_ref()
+                .map(|s| Self::setting_key_value("COMPILER_RT_PATH", s)),
+            picojson_path
+                .as_ref()
+                .map(|s| Self::setting_key_value("PICOJSON_PATH", s)),
             build_static_runtime
                 .as_ref()
                 .map(|s| Self::setting_key_value("BUILD_STATIC_RUNTIME", s)),
         ]
         .into_iter()
         .flatten()
+        .chain(self.cxx_standard.into_iter().flat_map(|standard| {
+            vec![
+                ("CMAKE_CXX_STANDARD".to_string(), standard.to_string()),
+                ("CMAKE_CXX_STANDARD_REQUIRED".to_string(), "ON".to_string()),
+            ]
+        }))
+        .chain(
+            Some(("BUILD_SHARED_LIBS".to_string(), "OFF".to_string()))
+                .filter(|_| self.prefer_static),
+        )
+        .chain(self.runtime.into_iter().flat_map(|runtime| runtime.cmake_defines()))
+        .chain(self.extra_defines_with_preset_flags())
+    }
+
+    /// `extra_defines`, with the compiler flags of the preset appended to the
+    /// `CMAKE_CXX_FLAGS` given there or, failing that, to `CXXFLAGS`, which CMake
+    /// ignores once `CMAKE_CXX_FLAGS` is defined.
+    fn extra_defines_with_preset_flags(&self) -> Vec<(String, String)> {
+        let mut defines = self.extra_defines.clone();
+        if let Some(preset) = &self.preset {
+            let position = defines.iter().rposition(|(key, _)| key == "CMAKE_CXX_FLAGS");
+            let flags = match position {
+                Some(position) => defines.remove(position).1,
+                None => std::env::var("CXXFLAGS").unwrap_or_default(),
+            };
+            let flags = format!("{} {}", flags, preset.cxx_flags());
+            defines.push(("CMAKE_CXX_FLAGS".to_string(), flags.trim().to_string()));
+        }
+        defines
+    }
+
+    /// The `USE_LLVM` setting, preferring LLVM from an active conda environment and
+    /// asking `llvm-config` for static libraries when static linkage is preferred.
+    ///
+    /// Set `TVM_BUILD_IGNORE_CONDA_LLVM` to use the system LLVM instead.
+    fn llvm_setting(&self, setting: &CMakeSetting) -> CMakeSetting {
+        match detect::resolve_llvm(setting) {
+            CMakeSetting::On if self.prefer_static => {
+                CMakeSetting::Path(PathBuf::from("llvm-config --link-static"))
+            }
+            CMakeSetting::Path(llvm_config) if self.prefer_static => {
+                CMakeSetting::Path(PathBuf::from(format!(
+                    "{} --link-static",
+                    llvm_config.display()
+                )))
+            }
+            setting => setting,
+        }
+    }
+
+    /// Every CMake define a build for `target` is configured with.
+    pub fn defines_for(&self, target: &Target) -> Vec<(String, String)> {
+        let use_metal = &self.effective_settings().use_metal;
+        let metal_defines = if cfg!(target_os = "macos")
+            && target.target_str.ends_with("apple-darwin")
+            && matches!(use_metal, Some(CMakeSetting::On))
+        {
+            detect::metal_defines()
+        } else {
+            vec![]
+        };
+
+        target
+            .cmake_defines
+            .iter()
+            .cloned()
+            .chain(metal_defines)
+            .chain(self.as_cmake_define_key_values())
+            .chain(if self.relocatable {
+                relocatable_defines(target)
+            } else {
+                vec![]
+            })
+            .collect()
+    }
+
+    /// Probe the host for the backends it supports, returning settings with each of
+    /// them enabled.
+    ///
+    /// Settings chosen explicitly should take precedence, combine them with
+    /// `UserSettings::or`.
+    pub fn autodetect_backends() -> UserSettings {
+        detect::available_backends()
+    }
+
+    /// Check the configuration is coherent before doing any work.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_revision(self.branch.as_deref().unwrap_or(DEFAULT_BRANCH))?;
+
+        if let Some(standard) = self.cxx_standard {
+            if !CXX_STANDARDS.contains(&standard) {
+                return Err(Error::InvalidSettings(format!(
+                    "unsupported C++ standard {}, expected one of {:?}",
+                    standard, CXX_STANDARDS
+                )));
+            }
+        }
+
+        if self.source_archive.is_some() && self.locked {
+            return Err(Error::InvalidSettings(
+                "sources from an archive can't be locked to commits".into(),
+            ));
+        }
+
+        if self.ephemeral && (self.output_path.is_some() || self.install_python.is_some()) {
+            return Err(Error::InvalidSettings(
+                "an ephemeral build can't be given an output path or install the Python package"
+                    .into(),
+            ));
+        }
+
+        if let Some(depth) = self.clone_depth {
+            if depth == 0 || depth > i32::MAX as u32 {
+                return Err(Error::InvalidSettings(format!(
+                    "the clone depth must be between 1 and {}, not {}",
+                    i32::MAX,
+                    depth
+                )));
+            }
+            if self.shallow_history().is_some() {
+                return Err(Error::InvalidSettings(
+                    "the clone depth can't be combined with shallow_since or shallow_since_tag"
+                        .into(),
+                ));
+            }
+            let revision = self.branch.as_deref().unwrap_or(DEFAULT_BRANCH);
+            if is_commit_sha(revision) && !self.fallback_to_full {
+                // The commit is only found if it is among the last `depth` commits of
+                // the default branch, which can't be known before cloning.
+                return Err(Error::InvalidSettings(format!(
+                    "the commit {} may be older than the {} commits a clone with that depth \
+                     fetches, build the tip of a branch instead, unset the clone depth or \
+                     enable fallback_to_full",
+                    revision, depth
+                )));
+            }
+        }
+
+        if self.update && (self.locked || self.offline || self.source_archive.is_some()) {
+            return Err(Error::InvalidSettings(
+                "updating the source can't be combined with locking, offline or a source archive"
+                    .into(),
+            ));
+        }
+
+        if self.runtime_only && self.install_python.is_some() {
+            return Err(Error::InvalidSettings(
+                "the Python package needs the compiler, it can't be installed by a runtime only \
+                 build"
+                    .into(),
+            ));
+        }
+
+        if self.locked && !self.track_remote_submodules.is_empty() {
+            return Err(Error::InvalidSettings(
+                "submodules can't both track their remote and be locked to commits".into(),
+            ));
+        }
+
+        if self.repository_path.is_some() {
+            // The source tree may be shared and read-only, so nothing may write to it.
+            let writes_source = [
+                ("an in-source build layout", self.build_layout == BuildLayout::InSource),
+                ("locking to commits", self.locked),
+                ("updating the source", self.update),
+                ("tracking remote submodules", !self.track_remote_submodules.is_empty()),
+                ("a source archive", self.source_archive.is_some()),
+                ("installing the Python package", self.install_python.is_some()),
+            ];
+            if let Some((option, _)) = writes_source.iter().find(|(_, enabled)| *enabled) {
+                return Err(Error::InvalidSettings(format!(
+                    "{} can't be used with an existing source tree, it would be written to",
+                    option
+                )));
+            }
+        }
+
+        if let Some(ssh_key) = &self.ssh_key {
+            if !ssh_key.is_file() {
+                return Err(Error::FileNotFound(ssh_key.display().to_string()));
+            }
+        }
+
+        if self.shallow_since.is_some() && self.shallow_since_tag.is_some() {
+            return Err(Error::InvalidSettings(
+                "limit the history by either a date or a tag, not both".into(),
+            ));
+        }
+
+        for (option, setting) in self.settings.path_settings() {
+            let unchecked = self
+                .unchecked_paths
+                .iter()
+                .any(|unchecked| unchecked.eq_ignore_ascii_case(option));
+            if let (Some(CMakeSetting::Path(path)), false) = (setting, unchecked) {
+                check_setting_path(option, path)?;
+            }
+        }
+
+        if let Some(preset) = &self.preset {
+            preset.validate(&self.settings, &self.generator())?;
+        }
+
+        if let Some(runtime) = &self.runtime {
+            runtime.validate(&self.settings)?;
+        }
+
+        let use_gtest = &self.settings.use_gtest;
+        if self.run_cpp_tests && matches!(use_gtest, None | Some(CMakeSetting::Off)) {
+            return Err(Error::InvalidSettings(
+                "running the C++ tests requires building with GoogleTest (use_gtest)".into(),
+            ));
+        }
+
+        let settings = self.effective_settings();
+        if let Some(sdk) = &settings.use_hexagon_sdk {
+            detect::validate_hexagon_sdk(sdk)?;
+        }
+        if let Some(toolchain) = &settings.hexagon_toolchain {
+            detect::validate_hexagon_toolchain(toolchain)?;
+        }
+
+        if self.build_docs && detect::find_executable("doxygen").is_none() {
+            return Err(Error::DoxygenNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+/// Where a revision's build directory lives relative to its source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BuildLayout {
+    /// `<revision>/build`, next to `<revision>/source`.
+    #[default]
+    Parallel,
+    /// `<revision>/source/build`, for tooling which expects the build inside the source tree.
+    InSource,
+}
+
+impl FromStr for BuildLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "parallel" => Ok(BuildLayout::Parallel),
+            "in-source" => Ok(BuildLayout::InSource),
+            _ => Err(format!(
+                "unknown build layout `{}`, expected one of: parallel, in-source",
+                s
+            )),
+        }
+    }
+}
+
+/// The CMake build type, `CMAKE_BUILD_TYPE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Profile {
+    #[default]
+    Debug,
+    Release,
+    /// Optimized, with debug info.
+    RelWithDebInfo,
+    /// Optimized for size.
+    MinSizeRel,
+}
+
+impl Profile {
+    /// The name CMake knows the build type by.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Profile::Debug => "Debug",
+            Profile::Release => "Release",
+            Profile::RelWithDebInfo => "RelWithDebInfo",
+            Profile::MinSizeRel => "MinSizeRel",
+        }
+    }
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "debug" => Ok(Profile::Debug),
+            "release" => Ok(Profile::Release),
+            "relwithdebinfo" => Ok(Profile::RelWithDebInfo),
+            "minsizerel" => Ok(Profile::MinSizeRel),
+            _ => Err(format!(
+                "unknown profile `{}`, expected one of: Debug, Release, RelWithDebInfo, MinSizeRel",
+                s
+            )),
+        }
+    }
+}
+
+/// A coordinated set of settings for a particular kind of build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// A minimal runtime for size constrained deployments: RTTI and the device backends
+    /// are turned off, fatal errors abort rather than throw and the runtime is linked
+    /// statically.
+    ///
+    /// Unlike most embedded builds this doesn't pass `-fno-exceptions`, TVM's runtime
+    /// reports errors by throwing and doesn't build without exceptions.
+    EmbeddedRuntime,
+}
+
+impl Preset {
+    /// The settings the preset consists of.
+    pub fn settings(&self) -> UserSettings {
+        match self {
+            Preset::EmbeddedRuntime => UserSettings {
+                use_rtti: Some(false),
+                use_cuda: Some(CMakeSetting::Off),
+                use_opencl: Some(CMakeSetting::Off),
+                use_vulkan: Some(CMakeSetting::Off),
+                use_metal: Some(CMakeSetting::Off),
+                use_rocm: Some(CMakeSetting::Off),
+                use_llvm: Some(CMakeSetting::Off),
+                use_rpc: Some(false),
+                use_openmp: Some(false),
+                use_graph_runtime_debug: Some(false),
+                use_relay_debug: Some(false),
+                build_static_runtime: Some(true),
+                ..UserSettings::default()
+            },
+        }
+    }
+
+    /// The compiler flags the preset needs beyond its settings, added to any others.
+    fn cxx_flags(&self) -> &'static str {
+        match self {
+            // TVM itself relies on exceptions, but dmlc's `LOG(FATAL)` can abort instead.
+            Preset::EmbeddedRuntime => "-DDMLC_LOG_FATAL_THROW=0",
+        }
+    }
+
+    /// Check explicitly chosen settings, and the CMake generator, don't contradict
+    /// the preset.
+    fn validate(&self, settings: &UserSettings, generator: &str) -> Result<(), Error> {
+        match self {
+            Preset::EmbeddedRuntime => {
+                if generator.starts_with("Visual Studio") {
+                    return Err(Error::InvalidSettings(format!(
+                        "the embedded runtime preset supports GCC and Clang, it can't be used \
+                         with the {} generator",
+                        generator
+                    )));
+                }
+
+                let enabled = |setting: &Option<CMakeSetting>| {
+                    matches!(setting, Some(CMakeSetting::On | CMakeSetting::Path(_)))
+                };
+                let conflicts = [
+                    ("use_rtti", settings.use_rtti == Some(true)),
+                    ("use_cuda", enabled(&settings.use_cuda)),
+                    ("use_opencl", enabled(&settings.use_opencl)),
+                    ("use_vulkan", enabled(&settings.use_vulkan)),
+                    ("use_metal", enabled(&settings.use_metal)),
+                    ("use_rocm", enabled(&settings.use_rocm)),
+                    ("use_llvm", enabled(&settings.use_llvm)),
+                ];
+
+                match conflicts.iter().find(|(_, conflicts)| *conflicts) {
+                    Some((setting, _)) => Err(Error::InvalidSettings(format!(
+                        "{} can't be enabled in the embedded runtime preset",
+                        setting
+                    ))),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "embedded-runtime" => Ok(Preset::EmbeddedRuntime),
+            _ => Err(format!(
+                "unknown preset `{}`, expected one of: embedded-runtime",
+                s
+            )),
+        }
+    }
+}
+
+/// The runtime used to execute compiled models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runtime {
+    /// The graph executor, running a JSON graph of fused operators.
+    Graph,
+    /// The ahead-of-time executor, compiling the graph into a single function.
+    Aot,
+    /// The Relay virtual machine, for models with dynamic shapes or control flow. It is
+    /// always part of the runtime, so the executors are turned off.
+    Vm,
+}
+
+impl Runtime {
+    /// The settings the runtime needs.
+    fn settings(&self) -> UserSettings {
+        UserSettings {
+            use_graph_runtime: Some(*self == Runtime::Graph),
+            ..UserSettings::default()
+        }
+    }
+
+    /// The CMake defines the runtime needs beyond its settings, TVM renamed the graph
+    /// runtime to the graph executor so both names are set.
+    fn cmake_defines(&self) -> Vec<(String, String)> {
+        let on_off = |enabled: bool| if enabled { "ON" } else { "OFF" }.to_string();
+        vec![
+            ("USE_GRAPH_EXECUTOR".to_string(), on_off(*self == Runtime::Graph)),
+            ("USE_AOT_EXECUTOR".to_string(), on_off(*self == Runtime::Aot)),
+        ]
+    }
+
+    /// Check explicitly chosen settings agree with the runtime.
+    fn validate(&self, settings: &UserSettings) -> Result<(), Error> {
+        let graph = *self == Runtime::Graph;
+        if settings.use_graph_runtime == Some(!graph) {
+            return Err(Error::InvalidSettings(format!(
+                "use_graph_runtime must be {} with the {} runtime",
+                if graph { "on" } else { "off" },
+                self
+            )));
+        }
+
+        if !graph && settings.use_graph_runtime_debug == Some(true) {
+            return Err(Error::InvalidSettings(format!(
+                "use_graph_runtime_debug requires the graph runtime, not the {} runtime",
+                self
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Runtime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Runtime::Graph => "graph",
+            Runtime::Aot => "aot",
+            Runtime::Vm => "vm",
+        })
+    }
+}
+
+impl FromStr for Runtime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "graph" => Ok(Runtime::Graph),
+            "aot" => Ok(Runtime::Aot),
+            "vm" => Ok(Runtime::Vm),
+            _ => Err(format!(
+                "unknown runtime `{}`, expected one of: graph, aot, vm",
+                s
+            )),
+        }
     }
 }
 
 pub struct Revision {
     revision: String,
-    output_path: Option<String>,
+    output_path: PathBuf,
+    build_layout: BuildLayout,
+    repository: Option<String>,
+    source_path: Option<PathBuf>,
 }
 
 impl Revision {
-    pub fn new(revision: String, output_path: Option<String>) -> Revision {
-        Revision { revision, output_path }
+    /// A revision built in `output_path`, by default in `tvm_build_directory()`.
+    pub fn new(revision: String, output_path: Option<String>) -> Result<Revision, Error> {
+        let output_path = match output_path {
+            Some(path) => PathBuf::from(path),
+            None => tvm_build_directory()?,
+        };
+
+        Ok(Revision {
+            revision,
+            output_path,
+            build_layout: BuildLayout::default(),
+            repository: None,
+            source_path: None,
+        })
     }
 
-    pub fn path(&self) -> PathBuf {
-        let path =
-            if let Some(path) = self.output_path.as_ref() {
-                PathBuf::from(path)
-            } else {
-                tvm_build_directory()
+    /// Record the URL the revision is fetched from.
+    pub fn with_repository(mut self, repository: String) -> Revision {
+        self.repository = Some(repository);
+        self
+    }
+
+    /// Build the source tree at `source_path`, which we don't own, instead of our own
+    /// checkout of the revision.
+    pub fn with_source_path(mut self, source_path: PathBuf) -> Revision {
+        self.source_path = Some(source_path);
+        self
+    }
+
+    /// The URL the revision was fetched from, when it is known.
+    pub fn repository(&self) -> Option<&str> {
+        self.repository.as_deref()
+    }
+
+    /// The branch, tag or commit this revision refers to.
+    pub fn name(&self) -> &str {
+        &self.revision
+    }
+
+    /// The revisions installed in `output_path`, by default in `tvm_build_directory()`,
+    /// sorted by name. Entries which don't look like a revision are skipped.
+    pub fn installed(output_path: Option<String>) -> Result<Vec<Revision>, Error> {
+        let output_path = match output_path {
+            Some(path) => PathBuf::from(path),
+            None => tvm_build_directory()?,
+        };
+        if !output_path.is_dir() {
+            return Ok(vec![]);
+        }
+
+        let mut revisions = vec![];
+        for entry in std::fs::read_dir(&output_path)? {
+            let entry = entry?;
+            let name = match entry.file_name().to_str() {
+                Some(name) if entry.file_type()?.is_dir() => name.to_string(),
+                _ => continue,
             };
-        path.join(&self.revision)
+            let revision = Revision::new(
+                unescape_path_component(&name),
+                Some(output_path.display().to_string()),
+            )?
+            .with_detected_build_layout();
+            if revision.is_installed() {
+                revisions.push(revision);
+            }
+        }
+
+        revisions.sort_by(|a, b| a.revision.cmp(&b.revision));
+        Ok(revisions)
+    }
+
+    /// Whether the revision's directory holds the `source` or `build` tree or the
+    /// manifest, as one created by a build does.
+    pub fn is_installed(&self) -> bool {
+        let directory = self.path();
+        directory.join("source").is_dir()
+            || directory.join("build").is_dir()
+            || BuildManifest::path(self).is_file()
+    }
+
+    /// Whether a build of the revision completed, installing its libraries.
+    pub fn is_built(&self) -> Result<bool, Error> {
+        Ok(!self.library_paths()?.is_empty())
+    }
+
+    /// The space the revision's directory takes up on disk, in bytes.
+    pub fn disk_usage(&self) -> Result<u64, Error> {
+        let directory = self.path();
+        if !directory.exists() {
+            return Ok(0);
+        }
+        directory_size(&directory)
+    }
+
+    pub fn with_build_layout(mut self, build_layout: BuildLayout) -> Revision {
+        self.build_layout = build_layout;
+        self
+    }
+
+    /// Use the layout of an existing build of this revision, if there is one.
+    pub fn with_detected_build_layout(self) -> Revision {
+        let in_source = self.with_build_layout(BuildLayout::InSource);
+        if in_source.build_path().exists() {
+            in_source
+        } else {
+            in_source.with_build_layout(BuildLayout::Parallel)
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.output_path.join(escape_path_component(&self.revision))
+    }
+
+    /// The file locked while the revision is built, see `lock::RevisionLock`.
+    pub(crate) fn lock_path(&self) -> PathBuf {
+        self.output_path.join(format!(".{}.lock", escape_path_component(&self.revision)))
     }
 
     pub fn source_path(&self) -> PathBuf {
-        self.path().join("source")
+        match &self.source_path {
+            Some(source_path) => source_path.clone(),
+            None => self.path().join("source"),
+        }
     }
 
     pub fn build_path(&self) -> PathBuf {
-        self.path().join("build")
+        match self.build_layout {
+            BuildLayout::Parallel => self.path().join("build"),
+            BuildLayout::InSource => self.source_path().join("build"),
+        }
+    }
+
+    /// The directory CMake configures and compiles in, the build path is the install prefix.
+    pub fn cmake_binary_path(&self) -> PathBuf {
+        self.build_path().join("build")
+    }
+
+    /// The directory the TVM libraries are installed to.
+    pub fn lib_path(&self) -> PathBuf {
+        self.build_path().join("lib")
+    }
+
+    /// The directories the TVM libraries are installed to, which on Windows includes
+    /// the one DLLs are installed to.
+    #[cfg(target_os = "windows")]
+    pub(crate) fn library_dirs(&self) -> Vec<PathBuf> {
+        windows::library_dirs(&self.build_path())
+    }
+
+    /// The directories the TVM libraries are installed to.
+    #[cfg(not(target_os = "windows"))]
+    pub(crate) fn library_dirs(&self) -> Vec<PathBuf> {
+        vec![self.lib_path()]
+    }
+
+    /// The installed TVM libraries.
+    pub fn library_paths(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut libraries = vec![];
+        for lib_path in self.library_dirs().iter().filter(|dir| dir.exists()) {
+            for entry in std::fs::read_dir(lib_path)? {
+                let path = entry?.path();
+                let is_library = matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("so" | "dylib" | "dll" | "a" | "lib")
+                );
+                let is_tvm = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("libtvm") || name.starts_with("tvm"));
+                if is_library && is_tvm {
+                    libraries.push(path);
+                }
+            }
+        }
+
+        libraries.sort();
+        Ok(libraries)
+    }
+
+    /// The SHA of the commit checked out in the source tree.
+    pub fn commit(&self) -> Result<String, Error> {
+        let repo = git2::Repository::open(self.source_path())?;
+        let commit = repo.head()?.peel_to_commit()?;
+        Ok(commit.id().to_string())
     }
 
-    pub fn build_for(&self, build_config: &BuildConfig, target: Target) -> Result<(), Error> {
+    /// The human readable version of the source, e.g. `0.8.0` or `v0.8.0-123-gabcdef0`.
+    ///
+    /// This is read from TVM's `version.py` or conda recipe, falling back to describing
+    /// the checkout by its nearest tag, or its short commit when no tag is reachable.
+    pub fn version(&self) -> Option<String> {
         let source_path = self.source_path();
+        let read = |path: PathBuf| std::fs::read_to_string(path).ok();
+
+        let unquote = |value: &str| value.trim().trim_matches(['"', '\'']).to_string();
+
+        // `__version__ = "0.8.0"`
+        let from_version_py = read(source_path.join("version.py")).and_then(|contents| {
+            contents.lines().find_map(|line| {
+                let value = line.trim().strip_prefix("__version__")?;
+                Some(unquote(value.trim_start().strip_prefix('=')?))
+            })
+        });
+
+        // `{% set version = "0.8.0" %}`
+        let from_conda_recipe = || {
+            let recipe = source_path.join("conda").join("recipe").join("meta.yaml");
+            read(recipe).and_then(|contents| {
+                contents.lines().find_map(|line| {
+                    let value = line.split("set version =").nth(1)?;
+                    Some(unquote(value.trim().trim_end_matches("%}")))
+                })
+            })
+        };
+
+        let from_git = || {
+            let repo = git2::Repository::open(&source_path).ok()?;
+            let describe = repo
+                .describe(
+                    git2::DescribeOptions::new()
+                        .describe_tags()
+                        .show_commit_oid_as_fallback(true),
+                )
+                .ok()?;
+            describe.format(None).ok()
+        };
+
+        from_version_py
+            .or_else(from_conda_recipe)
+            .or_else(from_git)
+            .filter(|version| !version.is_empty())
+    }
+
+    /// The commits the submodules are checked out at, keyed by their path.
+    pub fn submodule_commits(&self) -> Result<BTreeMap<String, String>, Error> {
+        let repo = git2::Repository::open(self.source_path())?;
+        let mut commits = BTreeMap::new();
+        for submodule in repo.submodules()? {
+            if let Some(id) = submodule.workdir_id().or_else(|| submodule.head_id()) {
+                commits.insert(submodule.path().display().to_string(), id.to_string());
+            }
+        }
+        Ok(commits)
+    }
+
+    /// A manifest of the source as currently checked out, configured with `defines`.
+    pub fn manifest(&self, defines: Vec<(String, String)>) -> Result<BuildManifest, Error> {
+        // Sources extracted from an archive have no history, and never change once extracted.
+        if !self.source_path().join(".git").exists() {
+            return Ok(BuildManifest {
+                source_head: String::new(),
+                submodules: BTreeMap::new(),
+                defines,
+                generator: None,
+                profile: None,
+                runtime_only: false,
+                config_cmake: None,
+                post_build_steps: BTreeSet::new(),
+            });
+        }
+
+        Ok(BuildManifest {
+            source_head: self.commit()?,
+            submodules: self.submodule_commits()?,
+            defines,
+            generator: None,
+            profile: None,
+            runtime_only: false,
+            config_cmake: None,
+            post_build_steps: BTreeSet::new(),
+        })
+    }
+
+    /// The defines the revision was last built with, from its manifest or otherwise
+    /// its CMake cache.
+    pub fn configured_defines(&self) -> Result<BTreeMap<String, String>, Error> {
+        if let Some(manifest) = BuildManifest::read(self)? {
+            return Ok(manifest.defines.into_iter().collect());
+        }
+
+        let cache_path = self.cmake_binary_path().join("CMakeCache.txt");
+        if !cache_path.exists() {
+            return Err(Error::FileNotFound(cache_path.display().to_string()));
+        }
+        read_cmake_cache(&cache_path)
+    }
+
+    /// The `USE_*` options enabled in the build's CMake cache, along with their values.
+    pub fn enabled_features(&self) -> Result<BTreeMap<String, String>, Error> {
+        let cache_path = self.cmake_binary_path().join("CMakeCache.txt");
+        if !cache_path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let features = read_cmake_cache(&cache_path)?
+            .into_iter()
+            .filter(|(key, value)| key.starts_with("USE_") && is_enabled(value))
+            .collect();
+        Ok(features)
+    }
+
+    /// The CMake configuration building this revision's source into `out_dir`.
+    fn cmake_config(
+        &self,
+        build_config: &BuildConfig,
+        out_dir: PathBuf,
+        defines: &[(String, String)],
+    ) -> cmake::Config {
+        let mut cmake_config = cmake::Config::new(self.source_path());
+
+        cmake_config
+            .generator(build_config.generator())
+            .out_dir(out_dir)
+            .profile(build_config.profile.as_str());
+
+        for (key, value) in defines {
+            debug!(%key, %value, "cmake define");
+            let _ = cmake_config.define(key, value);
+        }
+
+        // Whichever subscriber is in charge, ours or the application's, traces.
+        if LevelFilter::current() == LevelFilter::TRACE {
+            cmake_config.very_verbose(true);
+        }
+
+        cmake_config.keep_going(build_config.keep_going);
+
+        cmake_config.jobs(build_config.jobs());
+
+        if let Some(link_jobs) = build_config.link_jobs {
+            cmake_config.link_jobs(link_jobs);
+        }
+
+        if let Some(timeout) = build_config.configure_timeout {
+            cmake_config.configure_timeout(timeout);
+        }
+
+        if let Some(toolset) = &build_config.toolset {
+            cmake_config.generator_toolset(toolset);
+        }
+
+        if let Some(platform) = &build_config.platform {
+            cmake_config.platform(platform);
+        }
+
+        cmake_config
+    }
+
+    /// Configure the source with the settings of `build_config` in a scratch directory,
+    /// without building, returning the warnings CMake reported.
+    ///
+    /// This checks the settings are accepted by this source tree without the cost of a
+    /// build or leaving a cache behind, a failed configure is returned as
+    /// `Error::CMakeFailed`.
+    pub fn check_configure(
+        &self,
+        build_config: &BuildConfig,
+        target: &Target,
+    ) -> Result<Vec<String>, Error> {
+        let scratch = tempfile::tempdir()?;
+        let defines = build_config.defines_for(target);
+        let cmake_config = self.cmake_config(build_config, scratch.path().to_path_buf(), &defines);
+
+        if let Some(config_cmake) = &build_config.config_cmake {
+            BuildConfig::write_config_cmake(config_cmake, &cmake_config.binary_dir(), &defines)?;
+        }
+
+        let mut warnings = vec![];
+        let mut in_warning = false;
+        cmake_config.configure(|line| {
+            info!(target: "cmake", phase = "configure", "{}", line);
+            // CMake prints `CMake Warning ...:` followed by the indented message.
+            if line.starts_with("CMake Warning") || line.starts_with("CMake Deprecation Warning") {
+                in_warning = true;
+                warnings.push(line.to_string());
+            } else if in_warning && (line.is_empty() || line.starts_with(' ')) {
+                if let Some(warning) = warnings.last_mut() {
+                    if !line.trim().is_empty() {
+                        warning.push('\n');
+                        warning.push_str(line.trim());
+                    }
+                }
+            } else {
+                in_warning = false;
+            }
+        })?;
+
+        Ok(warnings)
+    }
+
+    pub fn build_for(self, build_config: &BuildConfig, target: Target) -> Result<BuildResult, Error> {
         let build_path = self.build_path();
 
         if !build_path.exists() {
@@ -582,29 +3172,706 @@ impl Revision {
             // .context(format!("the build directory does not exist: {:?}", build_path))?;
         }
 
-        let mut cmake_config = cmake::Config::new(source_path.clone());
+        debug!(target = %target.target_str, host = %target.host);
 
-        cmake_config
-            .generator("Unix Makefiles")
-            .out_dir(build_path.clone())
-            .target(&target.target_str)
-            .host(&target.host)
-            .profile("Debug");
+        let defines = build_config.defines_for(&target);
+        let cmake_config = self.cmake_config(build_config, build_path, &defines);
 
-        for (key, value) in build_config.as_cmake_define_key_values() {
-            let _ = cmake_config.define(key, value);
+        // Nothing to do if the last successful build used the same source and settings.
+        let mut manifest = build_config.manifest_for(&self, defines.clone())?;
+        let previous = BuildManifest::read(&self)?;
+        let built = !build_config.built_libraries(&self)?.is_empty();
+        let status = manifest.status(previous.as_ref(), built);
+        if !build_config.force && status == BuildStatus::UpToDate {
+            info!("the build is up to date");
+            // The requested steps after the build succeeded when it was built, the
+            // manifest records them only then, so only their outputs are reported.
+            let docs = Some(self.build_path().join("docs").join("html"))
+                .filter(|docs| build_config.build_docs && docs.is_dir());
+            let dependency_graph = Some(self.path().join(DEPENDENCY_GRAPH))
+                .filter(|graph| build_config.dependency_graph && graph.is_file());
+            build_config.report(BuildEvent::Finished);
+            return Ok(BuildResult {
+                version: self.version(),
+                build_dir: self.build_path(),
+                libraries: build_config.built_libraries(&self)?,
+                revision: self,
+                warnings: None,
+                up_to_date: true,
+                prebuilt: false,
+                defines: manifest.defines,
+                tests: None,
+                docs,
+                dependency_graph,
+                stripped: vec![],
+                temp_dir: None,
+            });
         }
 
-        if build_config.verbose {
-            cmake_config.very_verbose(true);
+        info!(
+            ?status,
+            force = build_config.force,
+            generator = ?manifest.generator,
+            profile = ?manifest.profile,
+            "building"
+        );
+
+        // Otherwise reuse the build tree so only what changed is recompiled, unless
+        // it is incompatible with the new settings.
+        if let Some(previous) = &previous {
+            if manifest.changes_toolchain(previous) {
+                info!("the toolchain changed, building from scratch");
+                cmake_config.clear_binary_dir()?;
+            } else if manifest.removes_defines(previous) {
+                info!("settings were removed, configuring from scratch");
+                cmake_config.clear_cache()?;
+            }
+        }
+
+        if let Some(config_cmake) = &build_config.config_cmake {
+            BuildConfig::write_config_cmake(config_cmake, &cmake_config.binary_dir(), &defines)?;
         }
 
-        cmake_config.build();
+        build_config.report(BuildEvent::Configuring);
+        cmake_config.configure(|line| info!(target: "cmake", phase = "configure", "{}", line))?;
 
+        let mut warnings = BuildWarnings::default();
+        let on_line = |line: &str| {
+            info!(target: "cmake", phase = "build", "{}", line);
+            if let Some(event) = progress::parse_build_line(line) {
+                build_config.report(event);
+            }
+            if build_config.collect_warnings {
+                warnings.record(line);
+            }
+        };
+        if build_config.runtime_only {
+            cmake_config.build_target("runtime", on_line)?;
+            self.install_runtime(&cmake_config)?;
+        } else {
+            cmake_config.build(on_line)?;
+        }
+
+        // The build tree is recorded now, the steps after the build only once they
+        // all succeeded so the next build runs them again after a failure.
+        let post_build_steps = std::mem::take(&mut manifest.post_build_steps);
+        manifest.write(&self)?;
+        let tests = build_config.cpp_tests(&cmake_config)?;
+        let docs = build_config.docs(&self)?;
+        let dependency_graph = build_config.dependency_graph(&cmake_config, &self)?;
+        let stripped = build_config.strip(&self)?;
+        manifest.post_build_steps = post_build_steps;
+        manifest.write(&self)?;
+        build_config.report(BuildEvent::Finished);
+
+        Ok(BuildResult {
+            version: self.version(),
+            build_dir: self.build_path(),
+            libraries: build_config.built_libraries(&self)?,
+            revision: self,
+            warnings: Some(warnings).filter(|_| build_config.collect_warnings),
+            up_to_date: false,
+            prebuilt: false,
+            defines: manifest.defines,
+            tests,
+            docs,
+            dependency_graph,
+            stripped,
+            temp_dir: None,
+        })
+    }
+
+    /// Install the runtime library built by the `runtime` target, as the `install` target
+    /// would install every library.
+    fn install_runtime(&self, cmake_config: &cmake::Config) -> Result<(), Error> {
+        let binary_dir = cmake_config.binary_dir();
+        // Multi-config generators like Visual Studio build into a directory per profile.
+        let candidates = [binary_dir.clone(), binary_dir.join(cmake_config.profile_name())];
+        let mut installed = false;
+        std::fs::create_dir_all(self.lib_path())?;
+        for dir in candidates.iter().filter(|dir| dir.is_dir()) {
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_file() && is_runtime_library(&path) {
+                    let file_name = path.file_name().expect("a file has a name");
+                    std::fs::copy(&path, self.lib_path().join(file_name))?;
+                    installed = true;
+                }
+            }
+        }
+
+        if !installed {
+            return Err(Error::FileNotFound(format!(
+                "the runtime library in {}",
+                binary_dir.display()
+            )));
+        }
         Ok(())
     }
+
+    /// Install the TVM Python package into the environment of `python`, as an editable
+    /// install pointing at this revision's source and libraries.
+    pub fn install_python(&self, python: &Path) -> Result<(), Error> {
+        info!(python = %python.display(), "installing the TVM Python package");
+        let output = std::process::Command::new(python)
+            .args(["-m", "pip", "install", "-e", "python"])
+            .current_dir(self.source_path())
+            .env("TVM_LIBRARY_PATH", self.lib_path())
+            .output()?;
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            debug!(target: "pip", "{}", line);
+        }
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(Error::PythonInstallFailed {
+                status: output.status,
+                output: String::from_utf8_lossy(&output.stderr).into_owned(),
+            })
+        }
+    }
+}
+
+/// Whether `path` is the TVM runtime library, e.g. `libtvm_runtime.so` or `tvm_runtime.dll`.
+fn is_runtime_library(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| {
+            name.starts_with("libtvm_runtime.") || name.starts_with("tvm_runtime.")
+        })
+}
+
+/// Check the path `option` is set to exists. A command with arguments, as `USE_LLVM`
+/// takes, is checked by the command, and a bare name is left for CMake to look up.
+fn check_setting_path(option: &str, path: &Path) -> Result<(), Error> {
+    if path.exists() {
+        return Ok(());
+    }
+
+    let value = path.to_string_lossy();
+    let command = value.split_whitespace().next().unwrap_or_default();
+    let is_path = command.contains('/') || command.contains(std::path::MAIN_SEPARATOR);
+    if is_path && !Path::new(command).exists() {
+        return Err(Error::SettingPathNotFound {
+            option: option.to_string(),
+            path: path.to_path_buf(),
+        });
+    }
+    Ok(())
+}
+
+/// A summary of the compiler warnings emitted by a build.
+#[derive(Debug, Default)]
+pub struct BuildWarnings {
+    /// The total number of warnings.
+    pub count: usize,
+    /// The first warning messages, up to a fixed limit.
+    pub messages: Vec<String>,
+}
+
+impl BuildWarnings {
+    fn record(&mut self, line: &str) {
+        // GCC and Clang report `file:line:col: warning: ...`, MSVC `file(line): warning C1234: ...`.
+        if line.contains(": warning:") || line.contains(": warning C") {
+            self.count += 1;
+            if self.messages.len() < MAX_WARNING_MESSAGES {
+                self.messages.push(line.trim().to_string());
+            }
+        }
+    }
+}
+
+/// The outcome of running TVM's C++ unit tests.
+#[derive(Debug, Default)]
+pub struct TestSummary {
+    /// The number of tests which passed.
+    pub passed: usize,
+    /// The number of tests which failed.
+    pub failed: usize,
+}
+
+impl TestSummary {
+    fn record(&mut self, line: &str) {
+        // CTest ends with `100% tests passed, 0 tests failed out of 12`.
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if let Some(["tests", "passed,", failed, "tests", "failed", "out", "of", total]) =
+            words.get(1..)
+        {
+            if let (Ok(failed), Ok(total)) = (failed.parse::<usize>(), total.parse::<usize>()) {
+                self.failed = failed;
+                self.passed = total.saturating_sub(failed);
+            }
+        }
+    }
 }
 
 pub struct BuildResult {
     pub revision: Revision,
+    /// The directory the build was installed to, see `Revision::build_path`.
+    pub build_dir: PathBuf,
+    /// The TVM libraries which were built, see `Revision::library_paths`.
+    pub libraries: Vec<PathBuf>,
+    /// The warnings emitted while compiling, when `BuildConfig::collect_warnings` is set.
+    pub warnings: Option<BuildWarnings>,
+    /// Whether the build was skipped because the previous build used the same source
+    /// and settings.
+    pub up_to_date: bool,
+    /// Whether a prebuilt release was installed instead of building from source.
+    pub prebuilt: bool,
+    /// The CMake defines the build was configured with, after the target's defines
+    /// and settings are merged. Empty for prebuilt releases.
+    pub defines: Vec<(String, String)>,
+    /// The results of the C++ unit tests, when `BuildConfig::run_cpp_tests` is set.
+    /// `None` for an up to date build, whose tests passed when it was built.
+    pub tests: Option<TestSummary>,
+    /// The generated C++ API documentation, when `BuildConfig::build_docs` is set.
+    pub docs: Option<PathBuf>,
+    /// The build's dependency graph, when `BuildConfig::dependency_graph` is set.
+    pub dependency_graph: Option<PathBuf>,
+    /// The libraries stripped and their sizes, when `BuildConfig::strip` is set. Empty
+    /// for an up to date build, whose libraries were stripped when it was built.
+    pub stripped: Vec<StrippedLibrary>,
+    /// The human readable version of the source, see `Revision::version`.
+    pub version: Option<String>,
+    /// The directory of an ephemeral build, removed along with the result.
+    pub(crate) temp_dir: Option<tempfile::TempDir>,
+}
+
+impl BuildResult {
+    /// Check the build is usable by loading the TVM runtime and resolving one of its
+    /// exported functions.
+    ///
+    /// This catches missing dependencies and similar breakage at build time instead
+    /// of at first use.
+    pub fn smoke_test(&self) -> Result<(), Error> {
+        let library = self
+            .revision
+            .lib_path()
+            .join(libloading::library_filename("tvm_runtime"));
+        let failed = |err: libloading::Error| Error::SmokeTestFailed {
+            library: library.clone(),
+            message: err.to_string(),
+        };
+
+        // Safety: loading the runtime only runs its static initializers, and the symbol
+        // is looked up but never called.
+        unsafe {
+            let runtime = libloading::Library::new(&library).map_err(failed)?;
+            runtime
+                .get::<unsafe extern "C" fn()>(b"TVMFuncListGlobalNames\0")
+                .map_err(failed)?;
+        }
+
+        info!(library = %library.display(), "smoke test passed");
+        Ok(())
+    }
+
+    /// Run a post build hook with the locations of the build in its environment.
+    pub fn run_post_build(&self, command: &mut std::process::Command) -> Result<(), Error> {
+        command
+            .env("TVM_HOME", self.revision.source_path())
+            .env("TVM_LIBRARY_PATH", self.revision.lib_path())
+            .env("TVM_INCLUDE_DIR", self.revision.build_path().join("include"));
+
+        info!(command = ?command, "running the post build hook");
+        let status = command.status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::PostBuildHookFailed {
+                command: format!("{:?}", command),
+                status,
+            })
+        }
+    }
+
+    /// The compiler flags needed to include TVM's headers.
+    pub fn cflags(&self) -> Vec<String> {
+        let source_path = self.revision.source_path();
+        std::iter::once(source_path.join("include"))
+            .chain(THIRD_PARTY_INCLUDES.iter().map(|include| source_path.join(include)))
+            .map(|include| format!("-I{}", include.display()))
+            .collect()
+    }
+
+    /// The linker flags needed to link against the TVM runtime.
+    pub fn ldflags(&self) -> Vec<String> {
+        vec![
+            format!("-L{}", self.revision.lib_path().display()),
+            "-ltvm_runtime".to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse the `set(KEY value)` lines rendered by `to_config_cmake`.
+    fn parse_config_cmake(config_cmake: &str) -> Vec<(String, String)> {
+        config_cmake
+            .lines()
+            .map(|line| {
+                let line = line.strip_prefix("set(").unwrap().strip_suffix(')').unwrap();
+                let (key, value) = line.split_once(' ').unwrap();
+                let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                    Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+                    None => value.to_string(),
+                };
+                (key.to_string(), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn config_cmake_matches_defines() {
+        let config = BuildConfig {
+            settings: UserSettings {
+                use_cuda: Some(CMakeSetting::On),
+                use_rpc: Some(false),
+                use_blas: Some("openblas".into()),
+                rocm_path: Some("/opt/my deps/rocm".into()),
+                use_tensorflow_path: Some("C:\\tf \"1\"".into()),
+                ..UserSettings::default()
+            },
+            ..BuildConfig::default()
+        };
+        let defines: Vec<_> = config.as_cmake_define_key_values().collect();
+        assert_eq!(defines.len(), 5);
+        assert_eq!(parse_config_cmake(&config.to_config_cmake()), defines);
+    }
+
+    #[test]
+    fn json_schema_has_every_setting() {
+        let schema = UserSettings::json_schema();
+        let properties = schema["properties"].as_object().unwrap();
+        let settings = serde_json::to_value(UserSettings::default()).unwrap();
+        let fields = settings.as_object().unwrap();
+        assert!(!fields.is_empty());
+        for field in fields.keys() {
+            assert!(properties.contains_key(field), "{} is missing from the schema", field);
+        }
+        assert_eq!(properties.len(), fields.len());
+    }
+
+    #[test]
+    fn validate_revision_rejects_invalid_refs() {
+        for revision in ["-foo", "--upload-pack=x", "main..other", ".hidden", "a b"] {
+            assert!(
+                matches!(validate_revision(revision), Err(Error::InvalidRevision(_))),
+                "{} was accepted",
+                revision
+            );
+        }
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        for revision in ["main", "v0.8.0", "feature/x", sha, "refs/tags/-rc1", "refs/heads/-x"] {
+            assert!(validate_revision(revision).is_ok(), "{} was rejected", revision);
+        }
+    }
+
+    #[test]
+    fn third_party_paths_emit_defines() {
+        let config = BuildConfig {
+            settings: UserSettings {
+                dlpack_path: Some("/opt/dlpack".into()),
+                dmlc_path: Some("/opt/dmlc-core".into()),
+                rang_path: Some("/opt/rang".into()),
+                compiler_rt_path: Some("/opt/compiler-rt".into()),
+                picojson_path: Some("/opt/picojson".into()),
+                ..UserSettings::default()
+            },
+            ..BuildConfig::default()
+        };
+        let defines: BTreeMap<_, _> = config.as_cmake_define_key_values().collect();
+        let expected = [
+            ("DLPACK_PATH", "/opt/dlpack"),
+            ("DMLC_PATH", "/opt/dmlc-core"),
+            ("RANG_PATH", "/opt/rang"),
+            ("COMPILER_RT_PATH", "/opt/compiler-rt"),
+            ("PICOJSON_PATH", "/opt/picojson"),
+        ];
+        assert_eq!(defines.len(), expected.len());
+        for (key, value) in expected {
+            assert_eq!(defines[key], value);
+        }
+    }
+
+    #[test]
+    fn build_directory_order() {
+        let build_dir = format!(".tvm_build_{}", std::env::consts::ARCH);
+        let home = PathBuf::from("/home/user");
+        assert_eq!(
+            build_directory(Some("/builds".into()), Some(home.clone())),
+            PathBuf::from("/builds")
+        );
+        assert_eq!(
+            build_directory(Some("".into()), Some(home.clone())),
+            home.join(&build_dir)
+        );
+        assert_eq!(build_directory(None, Some(home.clone())), home.join(&build_dir));
+        assert_eq!(build_directory(None, None), std::env::temp_dir().join(&build_dir));
+    }
+
+    /// Commit `contents` to the file `name` on the branch `main` of `repo`.
+    fn commit_file(repo: &git2::Repository, name: &str, contents: &str) -> git2::Oid {
+        let workdir = repo.workdir().unwrap();
+        std::fs::write(workdir.join(name), contents).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("tvm-build", "tvm-build@example.com").unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<_> = parent.iter().collect();
+        let commit = repo
+            .commit(None, &signature, &signature, "commit", &tree, &parents)
+            .unwrap();
+        repo.reference("refs/heads/main", commit, true, "commit").unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        commit
+    }
+
+    #[test]
+    fn checkout_error_keeps_missing_refs_apart() {
+        let repo_dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(repo_dir.path()).unwrap();
+        let first = commit_file(&repo, "CMakeLists.txt", "first");
+        commit_file(&repo, "CMakeLists.txt", "second");
+        std::fs::write(repo_dir.path().join("CMakeLists.txt"), "modified").unwrap();
+
+        let tree = repo.find_commit(first).unwrap().into_object();
+        let e = repo
+            .checkout_tree(&tree, Some(git2::build::CheckoutBuilder::new().safe()))
+            .unwrap_err();
+        match checkout_error("main", e) {
+            Error::CheckoutFailed { revision, .. } => assert_eq!(revision, "main"),
+            e => panic!("expected a failed checkout, got {:?}", e),
+        }
+        let not_found = git2::Error::from_str("reference not found");
+        assert!(matches!(checkout_error("main", not_found), Error::Git2(_)));
+    }
+
+    #[test]
+    fn dash_revisions_are_cloned_by_full_name() {
+        let origin_dir = tempfile::tempdir().unwrap();
+        let origin = git2::Repository::init(origin_dir.path()).unwrap();
+        let tagged = commit_file(&origin, "CMakeLists.txt", "tagged");
+        // Neither `git tag` nor libgit2 create such tags by name, but the ref is valid.
+        origin.reference("refs/tags/-rc1", tagged, false, "tag").unwrap();
+        let branched = commit_file(&origin, "CMakeLists.txt", "branched");
+        origin.reference("refs/heads/-x", branched, false, "branch").unwrap();
+        commit_file(&origin, "CMakeLists.txt", "main");
+
+        let output_dir = tempfile::tempdir().unwrap();
+        for (revision, contents) in [("refs/tags/-rc1", "tagged"), ("refs/heads/-x", "branched")] {
+            let config = BuildConfig {
+                repository: Some(format!("file://{}", origin_dir.path().display())),
+                output_path: Some(output_dir.path().display().to_string()),
+                branch: Some(revision.into()),
+                ..BuildConfig::default()
+            };
+            config.validate().unwrap();
+            let revision = config.get_revision().unwrap();
+            let cmake_lists = revision.source_path().join("CMakeLists.txt");
+            assert_eq!(std::fs::read_to_string(cmake_lists).unwrap(), contents);
+        }
+    }
+
+    #[test]
+    fn update_keeps_a_dirty_working_tree() {
+        let origin_dir = tempfile::tempdir().unwrap();
+        let origin = git2::Repository::init(origin_dir.path()).unwrap();
+        commit_file(&origin, "CMakeLists.txt", "first");
+        let output_dir = tempfile::tempdir().unwrap();
+        let config = |update| BuildConfig {
+            repository: Some(format!("file://{}", origin_dir.path().display())),
+            output_path: Some(output_dir.path().display().to_string()),
+            branch: Some("main".into()),
+            update,
+            ..BuildConfig::default()
+        };
+        let revision = config(false).get_revision().unwrap();
+        let cmake_lists = revision.source_path().join("CMakeLists.txt");
+        std::fs::write(&cmake_lists, "modified").unwrap();
+        commit_file(&origin, "CMakeLists.txt", "second");
+
+        match config(true).get_revision() {
+            Err(Error::DirtyWorkingTree { revision, .. }) => assert_eq!(revision, "main"),
+            Err(e) => panic!("expected a dirty working tree, got {:?}", e),
+            Ok(_) => panic!("the local changes were discarded"),
+        }
+        assert_eq!(std::fs::read_to_string(&cmake_lists).unwrap(), "modified");
+
+        std::fs::write(&cmake_lists, "first").unwrap();
+        config(true).get_revision().unwrap();
+        assert_eq!(std::fs::read_to_string(&cmake_lists).unwrap(), "second");
+    }
+
+    #[test]
+    fn settings_from_env() {
+        let _env = crate::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let vars = [
+            ("TVM_BUILD_USE_CUDA", "on"),
+            ("TVM_BUILD_USE_LLVM", "/usr/bin/llvm-config"),
+            ("TVM_BUILD_USE_RPC", "OFF"),
+            ("TVM_BUILD_USE_BLAS", "openblas"),
+        ];
+        for (var, value) in vars {
+            std::env::set_var(var, value);
+        }
+        let settings = UserSettings::from_env();
+        std::env::set_var("TVM_BUILD_USE_RPC", "sometimes");
+        let invalid = UserSettings::from_env();
+        for (var, _) in vars {
+            std::env::remove_var(var);
+        }
+
+        let settings = settings.unwrap();
+        assert!(matches!(settings.use_cuda, Some(CMakeSetting::On)));
+        assert!(matches!(
+            &settings.use_llvm,
+            Some(CMakeSetting::Path(path)) if path == Path::new("/usr/bin/llvm-config")
+        ));
+        assert_eq!(settings.use_rpc, Some(false));
+        assert_eq!(settings.use_blas.as_deref(), Some("openblas"));
+        assert!(settings.use_metal.is_none());
+        assert!(matches!(invalid, Err(Error::InvalidSettings(_))));
+    }
+
+    #[test]
+    fn shallow_clone_falls_back_to_full_clone() {
+        let origin_dir = tempfile::tempdir().unwrap();
+        let origin = git2::Repository::init(origin_dir.path()).unwrap();
+        commit_file(&origin, "CMakeLists.txt", "");
+        let output_dir = tempfile::tempdir().unwrap();
+
+        // The tag doesn't exist, so git can't tell which history to clone.
+        let shallow = |fallback_to_full| BuildConfig {
+            repository: Some(format!("file://{}", origin_dir.path().display())),
+            output_path: Some(output_dir.path().display().to_string()),
+            branch: Some("main".into()),
+            shallow_since_tag: Some("v9.9".into()),
+            fallback_to_full,
+            ..BuildConfig::default()
+        };
+        assert!(matches!(shallow(false).get_revision(), Err(Error::GitFailed { .. })));
+
+        let revision = shallow(true).get_revision().unwrap();
+        assert!(revision.source_path().join("CMakeLists.txt").is_file());
+    }
+
+    #[test]
+    fn cutlass_and_flashinfer_emit_defines() {
+        let config = BuildConfig {
+            settings: UserSettings {
+                use_cutlass: Some(CMakeSetting::On),
+                use_flashinfer: Some(CMakeSetting::Off),
+                ..UserSettings::default()
+            },
+            ..BuildConfig::default()
+        };
+        let defines: Vec<_> = config.as_cmake_define_key_values().collect();
+        assert_eq!(
+            defines,
+            [
+                ("USE_CUTLASS".to_string(), "ON".to_string()),
+                ("USE_FLASHINFER".to_string(), "OFF".to_string()),
+            ]
+        );
+        assert!(BuildConfig::default().as_cmake_define_key_values().next().is_none());
+    }
+
+    #[test]
+    fn clone_depth_rejects_commit() {
+        let config = BuildConfig {
+            branch: Some("0123456789abcdef0123456789abcdef01234567".into()),
+            clone_depth: Some(1),
+            ..BuildConfig::default()
+        };
+        assert!(matches!(config.validate(), Err(Error::InvalidSettings(_))));
+
+        let fallback = BuildConfig {
+            fallback_to_full: true,
+            ..config
+        };
+        assert!(fallback.validate().is_ok());
+
+        let branch = BuildConfig {
+            branch: Some("main".into()),
+            clone_depth: Some(1),
+            ..BuildConfig::default()
+        };
+        assert!(branch.validate().is_ok());
+    }
+
+    #[test]
+    fn clone_depth_falls_back_to_full_clone() {
+        let origin_dir = tempfile::tempdir().unwrap();
+        let origin = git2::Repository::init(origin_dir.path()).unwrap();
+        commit_file(&origin, "CMakeLists.txt", "");
+        let output_dir = tempfile::tempdir().unwrap();
+
+        // libgit2 can't fetch a limited history from a local repository, so the
+        // shallow clone fails.
+        let shallow = |fallback_to_full| BuildConfig {
+            repository: Some(format!("file://{}", origin_dir.path().display())),
+            output_path: Some(output_dir.path().display().to_string()),
+            branch: Some("main".into()),
+            clone_depth: Some(1),
+            fallback_to_full,
+            ..BuildConfig::default()
+        };
+        assert!(shallow(false).get_revision().is_err());
+
+        let revision = shallow(true).get_revision().unwrap();
+        let clone = git2::Repository::open(revision.source_path()).unwrap();
+        assert!(!clone.is_shallow());
+        assert!(revision.source_path().join("CMakeLists.txt").is_file());
+    }
+
+    #[test]
+    fn embedded_runtime_appends_cxx_flags() {
+        let _env = crate::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let cxx_flags = |config: &BuildConfig| {
+            config
+                .as_cmake_define_key_values()
+                .filter(|(key, _)| key == "CMAKE_CXX_FLAGS")
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>()
+        };
+        let config = BuildConfig {
+            preset: Some(Preset::EmbeddedRuntime),
+            extra_defines: vec![("CMAKE_CXX_FLAGS".into(), "-O2 -g".into())],
+            ..BuildConfig::default()
+        };
+        assert_eq!(cxx_flags(&config), ["-O2 -g -DDMLC_LOG_FATAL_THROW=0"]);
+
+        let cxxflags = std::env::var_os("CXXFLAGS");
+        std::env::set_var("CXXFLAGS", "-march=native");
+        let from_env = cxx_flags(&BuildConfig {
+            preset: Some(Preset::EmbeddedRuntime),
+            ..BuildConfig::default()
+        });
+        match cxxflags {
+            Some(cxxflags) => std::env::set_var("CXXFLAGS", cxxflags),
+            None => std::env::remove_var("CXXFLAGS"),
+        }
+        assert_eq!(from_env, ["-march=native -DDMLC_LOG_FATAL_THROW=0"]);
+    }
+
+    #[test]
+    fn embedded_runtime_rejects_visual_studio() {
+        let config = |generator: &str| BuildConfig {
+            preset: Some(Preset::EmbeddedRuntime),
+            generator: Some(generator.into()),
+            ..BuildConfig::default()
+        };
+        assert!(matches!(
+            config("Visual Studio 17 2022").validate(),
+            Err(Error::InvalidSettings(_))
+        ));
+        assert!(config("Ninja").validate().is_ok());
+    }
 }
@@ -0,0 +1,360 @@
+//! Detection of the SDKs and toolchains TVM's backends depend on.
+//!
+//! CMake's own detection often fails in ways which are hard to diagnose, so when
+//! a backend is simply turned `On` we try to locate its dependencies up front.
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, info, warn};
+
+use super::core::{CMakeSetting, Error, UserSettings};
+
+/// Find an executable, either given as a path or by name on the `PATH`.
+pub(crate) fn find_executable(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.components().count() > 1 {
+        return Some(path.to_path_buf()).filter(|path| path.is_file());
+    }
+
+    let exe_name = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&exe_name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// The Visual Studio generator used on Windows when Ninja can't be.
+const VISUAL_STUDIO_GENERATOR: &str = "Visual Studio 17 2022";
+
+/// The CMake generator to use when none is chosen, Ninja builds TVM considerably
+/// faster than Make so it is preferred when installed.
+///
+/// On Windows Ninja additionally needs the MSVC compiler on the `PATH`, as in a
+/// developer prompt, otherwise Visual Studio is used.
+pub(crate) fn default_generator() -> &'static str {
+    let ninja = find_executable("ninja")
+        .filter(|_| !cfg!(target_os = "windows") || find_executable("cl").is_some());
+    match ninja {
+        Some(ninja) => {
+            debug!(ninja = %ninja.display(), "found ninja, using the Ninja generator");
+            "Ninja"
+        }
+        None if cfg!(target_os = "windows") => {
+            debug!("ninja or cl were not found, using the Visual Studio generator");
+            VISUAL_STUDIO_GENERATOR
+        }
+        None => {
+            debug!("ninja was not found, using the Unix Makefiles generator");
+            "Unix Makefiles"
+        }
+    }
+}
+
+/// Find the most recent versioned SDK directory below `root`, as laid out by installers
+/// which keep each version side by side.
+fn newest_versioned_dir(root: &Path) -> Option<PathBuf> {
+    let mut versions: Vec<PathBuf> = std::fs::read_dir(root)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    versions.sort();
+    versions.pop()
+}
+
+/// Locate the Vulkan SDK, honoring `VULKAN_SDK` before looking in the LunarG
+/// installer's default locations.
+pub(crate) fn vulkan_sdk() -> Option<PathBuf> {
+    if let Some(sdk) = std::env::var_os("VULKAN_SDK").map(PathBuf::from) {
+        if sdk.exists() {
+            return Some(sdk);
+        }
+        warn!(sdk = %sdk.display(), "VULKAN_SDK is set but does not exist");
+    }
+
+    let (root, platform_dir) = if cfg!(target_os = "windows") {
+        (PathBuf::from("C:\\VulkanSDK"), None)
+    } else {
+        let platform_dir = if cfg!(target_os = "macos") {
+            "macOS"
+        } else {
+            std::env::consts::ARCH
+        };
+        (dirs::home_dir()?.join("VulkanSDK"), Some(platform_dir))
+    };
+
+    let version = newest_versioned_dir(&root)?;
+    let sdk = match platform_dir {
+        Some(platform_dir) => version.join(platform_dir),
+        None => version,
+    };
+
+    if sdk.exists() {
+        Some(sdk)
+    } else {
+        None
+    }
+}
+
+/// Whether the Vulkan headers are installed system wide, where CMake finds them unaided.
+fn vulkan_system_install() -> bool {
+    ["/usr/include/vulkan/vulkan.h", "/usr/local/include/vulkan/vulkan.h"]
+        .iter()
+        .any(|header| Path::new(header).exists())
+}
+
+/// Resolve `USE_VULKAN=ON` to the location of the SDK when we can find one.
+pub(crate) fn resolve_vulkan(setting: &CMakeSetting) -> CMakeSetting {
+    match setting {
+        CMakeSetting::On => match vulkan_sdk() {
+            Some(sdk) => {
+                info!(sdk = %sdk.display(), "using the detected Vulkan SDK");
+                CMakeSetting::Path(sdk)
+            }
+            None => {
+                if !vulkan_system_install() {
+                    warn!("Vulkan was requested but no Vulkan SDK could be found, set VULKAN_SDK or pass --use-vulkan <path>");
+                }
+                CMakeSetting::On
+            }
+        },
+        setting => setting.clone(),
+    }
+}
+
+/// The defines pointing CMake at the macOS SDK containing the Metal framework.
+///
+/// TVM links the Metal and Foundation frameworks from the SDK, which is only complete
+/// in a full Xcode install, so warn up front rather than failing late at link time.
+pub(crate) fn metal_defines() -> Vec<(String, String)> {
+    let output = std::process::Command::new("xcrun")
+        .args(["--sdk", "macosx", "--show-sdk-path"])
+        .output();
+    let sdk = match output {
+        Ok(output) if output.status.success() => {
+            PathBuf::from(String::from_utf8_lossy(&output.stdout).trim())
+        }
+        _ => {
+            warn!("Metal was requested but the macOS SDK could not be found, install Xcode");
+            return vec![];
+        }
+    };
+
+    let developer_dir = std::process::Command::new("xcode-select")
+        .arg("-p")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+    if developer_dir.contains("CommandLineTools") {
+        warn!("only the Xcode Command Line Tools are installed, building for Metal may fail without a full Xcode install");
+    }
+
+    let metal = sdk.join("System/Library/Frameworks/Metal.framework");
+    if !metal.exists() {
+        warn!(sdk = %sdk.display(), "Metal was requested but the SDK has no Metal framework");
+        return vec![];
+    }
+
+    info!(sdk = %sdk.display(), "using the macOS SDK for Metal");
+    vec![("CMAKE_OSX_SYSROOT".to_string(), sdk.display().to_string())]
+}
+
+/// The memory a single compile job may need, TVM's larger translation units take
+/// close to 2GB with optimizations on.
+const MEMORY_PER_JOB: u64 = 2 * 1024 * 1024 * 1024;
+
+/// The number of compile jobs which fit in the available memory, at most one per
+/// logical CPU.
+pub(crate) fn jobs_for_memory() -> usize {
+    let cpus = std::thread::available_parallelism().map_or(1, |cpus| cpus.get());
+    let memory = match futures::executor::block_on(heim::memory::memory()) {
+        Ok(memory) => memory,
+        Err(err) => {
+            warn!(%err, cpus, "could not read the available memory, using one job per CPU");
+            return cpus;
+        }
+    };
+
+    let available = memory
+        .available()
+        .get::<heim::units::information::byte>();
+    let jobs = ((available / MEMORY_PER_JOB) as usize).clamp(1, cpus);
+    info!(
+        jobs,
+        cpus,
+        available_mb = available / (1024 * 1024),
+        per_job_mb = MEMORY_PER_JOB / (1024 * 1024),
+        "limiting the compile jobs to fit in the available memory"
+    );
+    jobs
+}
+
+/// Check `sdk` looks like the root of a Hexagon SDK, TVM needs its headers in `incs`.
+pub(crate) fn validate_hexagon_sdk(sdk: &Path) -> Result<(), Error> {
+    let message = if !sdk.is_dir() {
+        "the directory does not exist"
+    } else if !sdk.join("incs").is_dir() {
+        "it has no `incs` directory, pass the root of the SDK"
+    } else {
+        return Ok(());
+    };
+
+    Err(Error::HexagonSdkNotFound {
+        path: sdk.to_path_buf(),
+        message: message.to_string(),
+    })
+}
+
+/// The compiler within a Hexagon toolchain.
+fn hexagon_compiler(toolchain: &Path) -> PathBuf {
+    toolchain
+        .join("bin")
+        .join(format!("hexagon-clang++{}", std::env::consts::EXE_SUFFIX))
+}
+
+/// Check `toolchain` contains the Hexagon compiler.
+pub(crate) fn validate_hexagon_toolchain(toolchain: &Path) -> Result<(), Error> {
+    if hexagon_compiler(toolchain).is_file() {
+        Ok(())
+    } else {
+        Err(Error::HexagonSdkNotFound {
+            path: toolchain.to_path_buf(),
+            message: "it has no bin/hexagon-clang++".to_string(),
+        })
+    }
+}
+
+/// Find the newest toolchain bundled with a Hexagon SDK, installed in
+/// `tools/HEXAGON_Tools/<version>/Tools`.
+pub(crate) fn hexagon_toolchain(sdk: &Path) -> Option<PathBuf> {
+    let toolchain = newest_versioned_dir(&sdk.join("tools").join("HEXAGON_Tools"))?.join("Tools");
+    if hexagon_compiler(&toolchain).is_file() {
+        info!(toolchain = %toolchain.display(), "using the Hexagon toolchain from the SDK");
+        Some(toolchain)
+    } else {
+        None
+    }
+}
+
+/// Set to skip looking for LLVM in the active conda environment.
+const IGNORE_CONDA_LLVM: &str = "TVM_BUILD_IGNORE_CONDA_LLVM";
+
+/// Locate `llvm-config` in the active conda environment.
+fn conda_llvm_config() -> Option<PathBuf> {
+    let prefix = PathBuf::from(std::env::var_os("CONDA_PREFIX")?);
+    let exe_name = format!("llvm-config{}", std::env::consts::EXE_SUFFIX);
+    // Conda installs executables into `Library/bin` on Windows.
+    [prefix.join("bin"), prefix.join("Library").join("bin")]
+        .iter()
+        .map(|dir| dir.join(&exe_name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Resolve `USE_LLVM=ON` to the `llvm-config` of the active conda environment, if it
+/// has one, as CMake would otherwise only find a system LLVM.
+pub(crate) fn resolve_llvm(setting: &CMakeSetting) -> CMakeSetting {
+    match setting {
+        CMakeSetting::On if std::env::var_os(IGNORE_CONDA_LLVM).is_none() => {
+            match conda_llvm_config() {
+                Some(llvm_config) => {
+                    info!(llvm_config = %llvm_config.display(), "using LLVM from the conda environment");
+                    CMakeSetting::Path(llvm_config)
+                }
+                None => {
+                    info!("using the system LLVM");
+                    CMakeSetting::On
+                }
+            }
+        }
+        setting => setting.clone(),
+    }
+}
+
+/// Locate a CUDA toolkit, honoring `CUDA_HOME` and `CUDA_PATH` before looking for
+/// `nvcc` and the default install location.
+fn cuda_toolkit() -> Option<PathBuf> {
+    let from_env = ["CUDA_HOME", "CUDA_PATH"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .map(PathBuf::from)
+        .find(|path| path.exists());
+
+    from_env
+        .or_else(|| {
+            // nvcc lives in `<toolkit>/bin`.
+            find_executable("nvcc")
+                .and_then(|nvcc| Some(nvcc.parent()?.parent()?.to_path_buf()))
+        })
+        .or_else(|| Some(PathBuf::from("/usr/local/cuda")).filter(|path| path.exists()))
+}
+
+/// Locate a ROCm install, honoring `ROCM_PATH` before the default install location.
+fn rocm() -> Option<PathBuf> {
+    std::env::var_os("ROCM_PATH")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(Some(PathBuf::from("/opt/rocm")))
+        .find(|path| path.exists())
+}
+
+/// The settings enabling each backend the host has the SDK or toolchain for.
+pub(crate) fn available_backends() -> UserSettings {
+    let mut settings = UserSettings::default();
+
+    if let Some(cuda) = cuda_toolkit() {
+        info!(toolkit = %cuda.display(), "enabling CUDA, found a CUDA toolkit");
+        settings.use_cuda = Some(CMakeSetting::Path(cuda));
+    }
+
+    if let Some(rocm) = rocm() {
+        info!(rocm = %rocm.display(), "enabling ROCm, found a ROCm install");
+        settings.use_rocm = Some(CMakeSetting::On);
+        settings.rocm_path = Some(rocm);
+    }
+
+    if cfg!(target_os = "macos") {
+        info!("enabling Metal, the host runs macOS");
+        settings.use_metal = Some(CMakeSetting::On);
+    }
+
+    if vulkan_sdk().is_some() || vulkan_system_install() {
+        info!("enabling Vulkan, found a Vulkan SDK or system install");
+        settings.use_vulkan = Some(CMakeSetting::On);
+    }
+
+    if let Some(llvm_config) = find_executable("llvm-config") {
+        info!(llvm_config = %llvm_config.display(), "enabling LLVM, found llvm-config");
+        settings.use_llvm = Some(CMakeSetting::Path(llvm_config));
+    }
+
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_generator_prefers_ninja() {
+        let _env = crate::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let with_ninja = tempfile::tempdir().unwrap();
+        let ninja = format!("ninja{}", std::env::consts::EXE_SUFFIX);
+        std::fs::write(with_ninja.path().join(ninja), "").unwrap();
+        let without_ninja = tempfile::tempdir().unwrap();
+
+        let path = std::env::var_os("PATH");
+        std::env::set_var("PATH", with_ninja.path());
+        let found = default_generator();
+        std::env::set_var("PATH", without_ninja.path());
+        let not_found = default_generator();
+        match path {
+            Some(path) => std::env::set_var("PATH", path),
+            None => std::env::remove_var("PATH"),
+        }
+
+        if cfg!(target_os = "windows") {
+            assert_eq!(not_found, VISUAL_STUDIO_GENERATOR);
+        } else {
+            assert_eq!(found, "Ninja");
+            assert_eq!(not_found, "Unix Makefiles");
+        }
+    }
+}
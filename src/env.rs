@@ -0,0 +1,112 @@
+//! The environment variables for using an installed revision, written as scripts
+//! which can be sourced by a shell.
+use std::path::PathBuf;
+
+use super::core::{Error, Revision};
+
+/// The name of the shell script written to the revision directory.
+const ENV_SH: &str = "env.sh";
+/// The name of the batch script written to the revision directory on Windows.
+const ENV_BAT: &str = "env.bat";
+
+/// A variable to set, `prepend` ones are search paths added in front of any existing value.
+struct Variable {
+    name: &'static str,
+    value: String,
+    prepend: bool,
+}
+
+/// The variable the dynamic loader searches for libraries in.
+fn library_path_variable() -> &'static str {
+    if cfg!(windows) {
+        "PATH"
+    } else if cfg!(target_os = "macos") {
+        "DYLD_LIBRARY_PATH"
+    } else {
+        "LD_LIBRARY_PATH"
+    }
+}
+
+fn variables(revision: &Revision) -> Vec<Variable> {
+    let source_path = revision.source_path();
+    let lib_path = revision.lib_path().display().to_string();
+    // On Windows DLLs may be installed next to, rather than in, the library directory.
+    let search_path = std::env::join_paths(revision.library_dirs())
+        .map(|paths| paths.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| lib_path.clone());
+    vec![
+        Variable {
+            name: "TVM_HOME",
+            value: source_path.display().to_string(),
+            prepend: false,
+        },
+        Variable {
+            name: "TVM_LIBRARY_PATH",
+            value: lib_path.clone(),
+            prepend: false,
+        },
+        Variable {
+            name: "PYTHONPATH",
+            value: source_path.join("python").display().to_string(),
+            prepend: true,
+        },
+        Variable {
+            name: library_path_variable(),
+            value: search_path,
+            prepend: true,
+        },
+    ]
+}
+
+/// A POSIX shell script exporting the environment, for `eval "$(tvm-build env main)"`.
+pub fn sh_script(revision: &Revision) -> String {
+    variables(revision)
+        .iter()
+        .map(|var| {
+            let value = var.value.replace('\\', "\\\\").replace('"', "\\\"").replace('$', "\\$");
+            if var.prepend {
+                format!("export {0}=\"{1}${{{0}:+:${0}}}\"\n", var.name, value)
+            } else {
+                format!("export {}=\"{}\"\n", var.name, value)
+            }
+        })
+        .collect()
+}
+
+/// A batch script setting the environment.
+pub fn bat_script(revision: &Revision) -> String {
+    variables(revision)
+        .iter()
+        .map(|var| {
+            if var.prepend {
+                format!("set \"{0}={1};%{0}%\"\r\n", var.name, var.value)
+            } else {
+                format!("set \"{}={}\"\r\n", var.name, var.value)
+            }
+        })
+        .collect()
+}
+
+/// The script for the host's shell.
+pub fn script(revision: &Revision) -> String {
+    if cfg!(windows) {
+        bat_script(revision)
+    } else {
+        sh_script(revision)
+    }
+}
+
+/// Write `env.sh`, and `env.bat` on Windows, into the revision directory, returning
+/// the path of the script for the host's shell.
+pub fn write(revision: &Revision) -> Result<PathBuf, Error> {
+    let sh_path = revision.path().join(ENV_SH);
+    std::fs::write(&sh_path, sh_script(revision))?;
+
+    if cfg!(windows) {
+        let bat_path = revision.path().join(ENV_BAT);
+        std::fs::write(&bat_path, bat_script(revision))?;
+        return Ok(bat_path);
+    }
+
+    Ok(sh_path)
+}
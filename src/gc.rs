@@ -0,0 +1,95 @@
+//! Pruning stale artifacts from long lived build trees.
+//!
+//! Incremental builds never remove the outputs of sources which were deleted or
+//! targets which were dropped, so they accumulate as a revision is rebuilt.
+use std::path::Path;
+
+use tracing::{debug, info};
+
+use super::cmake;
+use super::core::{Error, Revision};
+
+/// Remove the stale artifacts in `revision`'s build tree, returning how many files
+/// were removed.
+///
+/// Ninja knows its build graph so it removes everything the graph no longer produces.
+/// For other generators we only remove object files whose source no longer exists.
+pub(crate) fn collect(revision: &Revision) -> Result<usize, Error> {
+    let binary_dir = revision.cmake_binary_path();
+    if !binary_dir.exists() {
+        return Err(Error::DirectoryNotFound(binary_dir.display().to_string()));
+    }
+
+    if binary_dir.join("build.ninja").exists() {
+        let mut removed = 0;
+        cmake::clean_dead(&binary_dir, |line| {
+            info!(target: "ninja", "{}", line);
+            // `Cleaning... 3 files.`
+            if let Some(count) = line
+                .strip_prefix("Cleaning...")
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|count| count.parse().ok())
+            {
+                removed = count;
+            }
+        })?;
+        Ok(removed)
+    } else {
+        let cmake_files = binary_dir.join("CMakeFiles");
+        let mut removed = 0;
+        for entry in std::fs::read_dir(&cmake_files)? {
+            let target_dir = entry?.path();
+            let is_target_dir = target_dir.extension().is_some_and(|ext| ext == "dir");
+            if is_target_dir {
+                removed +=
+                    remove_orphaned_objects(&target_dir, &target_dir, &revision.source_path())?;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Remove the object files below `dir` whose source no longer exists.
+///
+/// Makefile generators lay objects out like their sources, `src/ir/expr.cc` is compiled
+/// to `<target_dir>/src/ir/expr.cc.o`.
+fn remove_orphaned_objects(dir: &Path, target_dir: &Path, source_path: &Path) -> Result<usize, Error> {
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            removed += remove_orphaned_objects(&path, target_dir, source_path)?;
+            continue;
+        }
+
+        let relative = path
+            .strip_prefix(target_dir)
+            .expect("objects are below their target directory");
+        let relative = relative.to_string_lossy();
+        let source = match relative
+            .strip_suffix(".o")
+            .or_else(|| relative.strip_suffix(".obj"))
+        {
+            Some(source) => source,
+            None => continue,
+        };
+
+        // Sources outside the source tree are mangled to `__/...`, leave them be.
+        if source.starts_with("__") || source_path.join(source).exists() {
+            continue;
+        }
+
+        debug!(object = %path.display(), "removing orphaned object");
+        std::fs::remove_file(&path)?;
+        removed += 1;
+
+        let dependencies = path.with_file_name(format!(
+            "{}.d",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        if dependencies.exists() {
+            std::fs::remove_file(dependencies)?;
+        }
+    }
+    Ok(removed)
+}
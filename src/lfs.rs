@@ -0,0 +1,53 @@
+//! Support for repositories which store files in git LFS.
+//!
+//! libgit2 doesn't run LFS filters, so a clone of such a repository only contains
+//! pointer files. Rather than build against the placeholders we fetch the real
+//! objects with the `git lfs` client, or refuse if that isn't enabled.
+use std::path::Path;
+
+use super::core::Error;
+
+/// Whether the working tree at `path` has files tracked by LFS.
+pub(crate) fn uses_lfs(path: &Path) -> Result<bool, Error> {
+    let attributes = path.join(".gitattributes");
+    if !attributes.exists() {
+        return Ok(false);
+    }
+
+    Ok(std::fs::read_to_string(attributes)?
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .any(|line| line.split_whitespace().any(|attr| attr == "filter=lfs")))
+}
+
+/// Make sure the LFS objects of the working tree at `path` are checked out.
+#[cfg(feature = "git-lfs")]
+pub(crate) fn checkout_objects(path: &Path) -> Result<(), Error> {
+    if !uses_lfs(path)? {
+        return Ok(());
+    }
+
+    tracing::info!(path = %path.display(), "fetching git LFS objects");
+    let lfs_required = || Error::GitLfsRequired(path.display().to_string());
+    let status = std::process::Command::new("git")
+        .current_dir(path)
+        .args(["lfs", "pull"])
+        .status()
+        .map_err(|_| lfs_required())?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(lfs_required())
+    }
+}
+
+/// Make sure the LFS objects of the working tree at `path` are checked out.
+#[cfg(not(feature = "git-lfs"))]
+pub(crate) fn checkout_objects(path: &Path) -> Result<(), Error> {
+    if uses_lfs(path)? {
+        Err(Error::GitLfsRequired(path.display().to_string()))
+    } else {
+        Ok(())
+    }
+}
@@ -1,47 +1,368 @@
-use crate::core::Revision;
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 use tracing::{self, info};
 
+mod archive;
+mod build_script;
+mod builder;
+mod checksum;
+mod cmake;
 mod core;
+mod detect;
+mod env;
+mod gc;
+mod lfs;
+mod lock;
+mod manifest;
+mod prebuilt;
+mod progress;
+mod revision_spec;
+mod strip;
 mod targets;
+#[cfg(target_os = "windows")]
+mod windows;
 
-use targets::local_target;
+use lock::RevisionLock;
+use targets::{local_target, target_from_triple};
 
-pub use self::core::{BuildConfig, UserSettings, CMakeSetting};
+pub use self::build_script::{
+    CONFIG_VAR, OUTPUT_PATH_VAR, REPOSITORY_VAR, REVISION_VAR, TARGET_VAR,
+};
+pub use self::builder::BuildConfigBuilder;
+pub use self::core::{
+    BuildConfig, BuildLayout, BuildResult, BuildWarnings, CMakeSetting, Preset, Profile,
+    Revision, Runtime, TestSummary, UserSettings,
+};
+pub use self::manifest::{diff_revisions, BuildManifest, BuildStatus, SettingDiff};
+pub use self::progress::BuildEvent;
+pub use self::revision_spec::RevisionSpec;
+pub use self::strip::StrippedLibrary;
+
+/// Held by tests which change the environment, which every test in the process shares.
+#[cfg(test)]
+pub(crate) static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// The locations, relative to the source tree, the TVM Python package has lived at.
+const PYTHON_PACKAGE_LAYOUTS: &[&str] = &["python/tvm", "python/tvm-ffi"];
 
 #[derive(Serialize, Deserialize)]
 pub struct VersionConfig {
     pub tvm_python_path: PathBuf,
+    /// The layout `tvm_python_path` was found at, relative to the source tree.
+    pub tvm_python_layout: String,
+    /// The installed TVM libraries.
+    pub library_paths: Vec<PathBuf>,
+    /// The commit the installation was built from.
+    pub commit: Option<String>,
+    /// The human readable version of the installation, e.g. `0.8.0`.
+    pub version: Option<String>,
+    /// The `USE_*` options enabled in the build and their values.
+    pub enabled_features: BTreeMap<String, String>,
 }
 
 /// Build TVM given a build configuration.
-#[tracing::instrument]
+///
+/// Builds of the same revision in the same directory, also by other processes, wait
+/// for each other rather than running at once.
+///
+/// All output is reported through `tracing`, when `build_config.log_level` is set and
+/// the caller hasn't installed a subscriber of their own a subscriber logging at that
+/// level is installed for the duration of the build.
 pub fn build(build_config: core::BuildConfig) -> Result<core::BuildResult, core::Error> {
+    // `tracing::dispatcher::has_been_set` stays set once any subscriber was installed,
+    // including the one of an earlier build, so look for the no-op default instead.
+    let has_subscriber = !tracing::dispatcher::get_default(|dispatch| {
+        dispatch.is::<tracing_core::subscriber::NoSubscriber>()
+    });
+    match build_config.log_level {
+        Some(level) if !has_subscriber => {
+            let subscriber = tracing_subscriber::fmt().with_max_level(level).finish();
+            tracing::subscriber::with_default(subscriber, || build_with(build_config))
+        }
+        _ => build_with(build_config),
+    }
+}
+
+#[tracing::instrument]
+fn build_with(mut build_config: core::BuildConfig) -> Result<core::BuildResult, core::Error> {
     info!("tvm_build::build");
+    build_config.validate()?;
+
+    let temp_dir = if build_config.ephemeral {
+        let temp_dir = tempfile::Builder::new().prefix("tvm-build-").tempdir()?;
+        info!(path = %temp_dir.path().display(), "building in a temporary directory");
+        build_config.output_path = Some(temp_dir.path().display().to_string());
+        Some(temp_dir)
+    } else {
+        None
+    };
+
+    let target = resolve_target(&build_config)?;
+    let _lock = RevisionLock::acquire(&build_config.revision()?)?;
+
+    let mut result = match install_prebuilt(&build_config, &target)? {
+        Some(result) => result,
+        None => {
+            let rev = build_config.get_revision()?;
+
+            let result = rev.build_for(&build_config, target)?;
+
+            if let Some(python) = &build_config.install_python {
+                result.revision.install_python(python)?;
+            }
+
+            result
+        }
+    };
+
+    if build_config.write_env {
+        let path = env::write(&result.revision)?;
+        info!(path = %path.display(), "wrote the environment script");
+    }
+
+    if let Some(hook) = &mut build_config.post_build {
+        result.run_post_build(hook)?;
+    }
+
+    result.temp_dir = temp_dir;
+    Ok(result)
+}
+
+/// Build TVM from a consumer's `build.rs` and print the `cargo:` directives linking
+/// against it.
+///
+/// To configure the build in code instead, call `build` and then
+/// `BuildResult::emit_cargo_metadata`.
+///
+/// The build is configured by a `tvm-build.toml` in the crate root, or the file named by
+/// `TVM_BUILD_CONFIG`, with the top level keys `revision`, `repository`, `output_path`,
+/// `target` and `library` (the library to link, by default `tvm_runtime`) and a
+/// `[settings]` table of `UserSettings`. The environment variables `TVM_BUILD_REVISION`,
+/// `TVM_BUILD_REPOSITORY`, `TVM_BUILD_OUTPUT_PATH` and `TVM_BUILD_TARGET`, and settings
+/// given as `TVM_BUILD_<SETTING>` (see `UserSettings::from_env`), take precedence over the
+/// file.
+///
+/// The include directories are emitted as `cargo:include`, which dependents can read as
+/// `DEP_<links>_INCLUDE` when the crate sets `links`.
+pub fn build_from_env() -> Result<core::BuildResult, core::Error> {
+    build_script::build_from_env()
+}
+
+/// Whether building with `build_config` would do anything, and why, without
+/// building or fetching anything.
+///
+/// The source is compared as currently checked out, changes upstream which haven't
+/// been fetched aren't taken into account.
+pub fn build_status(build_config: &core::BuildConfig) -> Result<BuildStatus, core::Error> {
+    let target = resolve_target(build_config)?;
+    let rev = build_config.revision()?;
+    if !rev.source_path().exists() {
+        return Ok(BuildStatus::NotBuilt);
+    }
+
+    let manifest = build_config.manifest_for(&rev, build_config.defines_for(&target))?;
+    let previous = BuildManifest::read(&rev)?;
+    let built = !build_config.built_libraries(&rev)?.is_empty();
+    Ok(manifest.status(previous.as_ref(), built))
+}
+
+/// Find an existing build of `build_config` without building, fetching or otherwise
+/// changing anything, the read-only companion to `build`.
+///
+/// Returns `None` unless the revision was built successfully with the same source and
+/// settings, exactly when `build` would find it up to date.
+pub fn locate(build_config: &core::BuildConfig) -> Result<Option<core::BuildResult>, core::Error> {
+    if build_status(build_config)? != BuildStatus::UpToDate {
+        return Ok(None);
+    }
+
+    let rev = build_config.revision()?;
+    let defines = BuildManifest::read(&rev)?
+        .map(|manifest| manifest.defines)
+        .unwrap_or_default();
+    Ok(Some(core::BuildResult {
+        version: rev.version(),
+        build_dir: rev.build_path(),
+        libraries: build_config.built_libraries(&rev)?,
+        revision: rev,
+        warnings: None,
+        up_to_date: true,
+        prebuilt: false,
+        defines,
+        tests: None,
+        docs: None,
+        dependency_graph: None,
+        stripped: vec![],
+        temp_dir: None,
+    }))
+}
+
+/// Check the settings of `build_config` are accepted by the revision's source by
+/// running only CMake's configure step, in a scratch directory, returning the warnings
+/// CMake reported.
+///
+/// Unlike a build this leaves no CMake cache behind, the source is still fetched if
+/// it isn't already.
+pub fn check(build_config: core::BuildConfig) -> Result<Vec<String>, core::Error> {
+    build_config.validate()?;
+    let target = resolve_target(&build_config)?;
+    let _lock = RevisionLock::acquire(&build_config.revision()?)?;
     let rev = build_config.get_revision()?;
-    let target = local_target();
+    rev.check_configure(&build_config, &target)
+}
+
+/// Install the matching prebuilt release when one is configured and exists.
+fn install_prebuilt(
+    build_config: &core::BuildConfig,
+    target: &targets::Target,
+) -> Result<Option<core::BuildResult>, core::Error> {
+    let base_url = match &build_config.prebuilt_url {
+        Some(base_url) => base_url,
+        None => return Ok(None),
+    };
+
+    let rev = build_config.revision()?;
+    let expected_sha256 = build_config.prebuilt_sha256.as_deref();
+    if !prebuilt::install(base_url, &rev, target, expected_sha256)? {
+        info!("no prebuilt release matches, building from source");
+        return Ok(None);
+    }
 
-    rev.build_for(&build_config, target)?;
+    Ok(Some(core::BuildResult {
+        version: rev.version(),
+        build_dir: rev.build_path(),
+        libraries: rev.library_paths()?,
+        revision: rev,
+        warnings: None,
+        up_to_date: false,
+        prebuilt: true,
+        defines: vec![],
+        tests: None,
+        docs: None,
+        dependency_graph: None,
+        stripped: vec![],
+        temp_dir: None,
+    }))
+}
 
-    // info!(target = target.target_str);
-    // info!(dst = dst.display().to_string());
+/// Fetch a revision and record the exact commits and settings it would be built with
+/// in its lock file, without building it.
+///
+/// Later builds with `BuildConfig::locked` check out exactly these commits. The lock is
+/// kept apart from the manifest of the last build, which tells whether a build is up
+/// to date.
+pub fn lock(build_config: core::BuildConfig) -> Result<BuildManifest, core::Error> {
+    build_config.validate()?;
+    let target = resolve_target(&build_config)?;
+    let _lock = RevisionLock::acquire(&build_config.revision()?)?;
+    let rev = build_config.get_revision()?;
+    let manifest = build_config.manifest_for(&rev, build_config.defines_for(&target))?;
+    manifest.write_lock(&rev)?;
+    info!(path = %BuildManifest::lock_file_path(&rev).display(), "wrote the lock");
+    Ok(manifest)
+}
 
-    Ok(core::BuildResult { revision: rev })
+/// The target to build for, the host unless another is configured.
+fn resolve_target(build_config: &core::BuildConfig) -> Result<targets::Target, core::Error> {
+    match &build_config.target {
+        Some(triple) => target_from_triple(triple),
+        None => Ok(local_target()),
+    }
 }
 
+/// Remove an installed revision, its source, build and manifest.
+///
+/// As the directory is removed recursively, one outside the default build directory,
+/// in a user supplied `output_path`, is only removed when it looks like a revision
+/// directory, holding the `source` or `build` tree or the manifest.
 pub fn uninstall(revision: String, output_path: Option<String>) -> Result<(), core::Error> {
-    let revision = Revision::new(revision, output_path);
+    core::validate_revision(&revision)?;
+    let revision = Revision::new(revision, output_path)?;
     let directory = revision.path();
-    std::fs::remove_dir(directory)?;
+    if !directory.is_dir() {
+        return Err(core::Error::DirectoryNotFound(
+            directory.display().to_string(),
+        ));
+    }
+
+    let in_build_directory = directory.starts_with(core::tvm_build_directory()?);
+    if !in_build_directory && !revision.is_installed() {
+        return Err(core::Error::InvalidSettings(format!(
+            "refusing to remove {}, it doesn't look like a TVM build",
+            directory.display()
+        )));
+    }
+
+    info!(path = %directory.display(), "removing the revision");
+    std::fs::remove_dir_all(directory)?;
     Ok(())
 }
 
-pub fn version_config(revision: String) -> Result<VersionConfig, core::Error> {
-    let rev = Revision::new(revision, None);
+/// The revisions installed in `output_path`, by default the build directory.
+///
+/// `Revision::is_built` and `Revision::disk_usage` tell whether each was built
+/// successfully and how much space it takes up.
+pub fn list_revisions(output_path: Option<String>) -> Result<Vec<Revision>, core::Error> {
+    Revision::installed(output_path)
+}
+
+/// A script for the host's shell setting `TVM_HOME`, `TVM_LIBRARY_PATH`, `PYTHONPATH`
+/// and the library search path for an installed revision.
+pub fn env(revision: String, output_path: Option<String>) -> Result<String, core::Error> {
+    core::validate_revision(&revision)?;
+    let revision = Revision::new(revision, output_path)?.with_detected_build_layout();
+    if !revision.path().exists() {
+        return Err(core::Error::DirectoryNotFound(
+            revision.path().display().to_string(),
+        ));
+    }
+    Ok(env::script(&revision))
+}
+
+/// Remove stale artifacts from an installed revision's build tree without a full
+/// clean, returning how many files were removed.
+pub fn gc(revision: String, output_path: Option<String>) -> Result<usize, core::Error> {
+    core::validate_revision(&revision)?;
+    let revision = Revision::new(revision, output_path)?.with_detected_build_layout();
+    gc::collect(&revision)
+}
+
+/// Get the configuration of an installed revision.
+///
+/// The Python package is looked up at `python_subpath` if provided, and otherwise
+/// at each of the known repository layouts in turn.
+pub fn version_config(
+    revision: String,
+    python_subpath: Option<String>,
+) -> Result<VersionConfig, core::Error> {
+    core::validate_revision(&revision)?;
+    let rev = Revision::new(revision, None)?.with_detected_build_layout();
+    let source_path = rev.source_path();
+
+    let layouts = match &python_subpath {
+        Some(subpath) => vec![subpath.as_str()],
+        None => PYTHON_PACKAGE_LAYOUTS.to_vec(),
+    };
+
+    let layout = layouts
+        .iter()
+        .find(|layout| source_path.join(layout).exists())
+        .ok_or_else(|| {
+            core::Error::DirectoryNotFound(format!(
+                "no TVM Python package found in {} at any of: {}",
+                source_path.display(),
+                layouts.join(", ")
+            ))
+        })?;
+
     let version = VersionConfig {
-        tvm_python_path: rev.source_path().join("python").join("tvm"),
+        tvm_python_path: source_path.join(layout),
+        tvm_python_layout: layout.to_string(),
+        library_paths: rev.library_paths()?,
+        commit: rev.commit().ok(),
+        version: rev.version(),
+        enabled_features: rev.enabled_features()?,
     };
     Ok(version)
 }
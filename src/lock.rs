@@ -0,0 +1,41 @@
+//! Serializing builds of the same revision across processes.
+//!
+//! Builds sharing a build directory, e.g. CI jobs sharing a cache, would otherwise
+//! race on a revision's source and build trees. The lock is an advisory lock on a
+//! file next to the revision's directory, as cleaning removes the directory itself,
+//! which the operating system releases when the process exits, even if it crashed.
+use std::fs::{File, OpenOptions};
+
+use fs2::FileExt;
+use tracing::info;
+
+use super::core::{Error, Revision};
+
+/// Held for as long as a process is building a revision, released on drop.
+#[derive(Debug)]
+pub(crate) struct RevisionLock {
+    _file: File,
+}
+
+impl RevisionLock {
+    /// Lock `revision`, waiting for another process building it to finish first.
+    pub(crate) fn acquire(revision: &Revision) -> Result<RevisionLock, Error> {
+        let path = revision.lock_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        if file.try_lock_exclusive().is_err() {
+            info!(path = %path.display(), "waiting for another build of the revision to finish");
+            file.lock_exclusive()?;
+        }
+
+        Ok(RevisionLock { _file: file })
+    }
+}
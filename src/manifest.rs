@@ -0,0 +1,274 @@
+//! A record of how a revision was built, persisted next to the build so later
+//! invocations can tell whether anything changed.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::core::{Error, Profile, Revision};
+
+const MANIFEST_FILE: &str = "tvm-build-manifest.json";
+const LOCK_FILE: &str = "tvm-build-lock.json";
+
+/// The defines which select the toolchain, CMake can't change these in an existing
+/// build tree.
+const TOOLCHAIN_DEFINES: &[&str] = &[
+    "CMAKE_C_COMPILER",
+    "CMAKE_CXX_COMPILER",
+    "CMAKE_TOOLCHAIN_FILE",
+    "CMAKE_SYSTEM_NAME",
+    "CMAKE_SYSTEM_PROCESSOR",
+    "CMAKE_OSX_ARCHITECTURES",
+    "CMAKE_OSX_SYSROOT",
+];
+
+/// Whether a revision needs to be built, and why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildStatus {
+    /// The revision has not been built, or its libraries are missing.
+    NotBuilt,
+    /// The source or submodules are at different commits than the last build.
+    SourceChanged,
+    /// The settings differ from those of the last build, or it didn't run all of the
+    /// steps after the build which are requested now.
+    SettingsChanged,
+    /// The last build used the same source and settings.
+    UpToDate,
+}
+
+/// A define which differs between two builds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SettingDiff {
+    pub key: String,
+    /// The value in the first build, `None` when it isn't set.
+    pub a: Option<String>,
+    /// The value in the second build, `None` when it isn't set.
+    pub b: Option<String>,
+}
+
+/// The defines which differ between the builds of two revisions, sorted by key.
+///
+/// The defines are read from each build's manifest or, for builds without one, from
+/// its CMake cache.
+pub fn diff_revisions(a: &Revision, b: &Revision) -> Result<Vec<SettingDiff>, Error> {
+    let a_defines = a.configured_defines()?;
+    let b_defines = b.configured_defines()?;
+
+    let keys: BTreeSet<&String> = a_defines.keys().chain(b_defines.keys()).collect();
+    let diffs = keys
+        .into_iter()
+        .filter(|key| a_defines.get(*key) != b_defines.get(*key))
+        .map(|key| SettingDiff {
+            key: key.clone(),
+            a: a_defines.get(key).cloned(),
+            b: b_defines.get(key).cloned(),
+        })
+        .collect();
+    Ok(diffs)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildManifest {
+    /// The commit the source tree was at.
+    pub source_head: String,
+    /// The commits the submodules were at, keyed by their path.
+    #[serde(default)]
+    pub submodules: BTreeMap<String, String>,
+    /// The CMake defines the build was configured with.
+    pub defines: Vec<(String, String)>,
+    /// The CMake generator the build was configured with.
+    #[serde(default)]
+    pub generator: Option<String>,
+    /// The CMake build type the build was configured with.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Whether only the runtime library was built.
+    #[serde(default)]
+    pub runtime_only: bool,
+    /// The SHA-256 digest of the `config.cmake` the build was configured with, see
+    /// `BuildConfig::config_cmake`.
+    #[serde(default)]
+    pub config_cmake: Option<String>,
+    /// The steps which ran after the build, see `BuildConfig::post_build_steps`. Only
+    /// recorded once all of them succeeded.
+    #[serde(default)]
+    pub post_build_steps: BTreeSet<String>,
+}
+
+impl BuildManifest {
+    pub fn path(revision: &Revision) -> PathBuf {
+        revision.build_path().join(MANIFEST_FILE)
+    }
+
+    /// The manifest `tvm_build::lock` writes, kept apart from the manifest of the last
+    /// build since nothing was built with it.
+    pub fn lock_file_path(revision: &Revision) -> PathBuf {
+        revision.build_path().join(LOCK_FILE)
+    }
+
+    /// Read the manifest of the last successful build of `revision`, if any.
+    pub fn read(revision: &Revision) -> Result<Option<BuildManifest>, Error> {
+        Self::read_from(&Self::path(revision))
+    }
+
+    /// Read the commits `BuildConfig::locked` checks out, those written by
+    /// `tvm_build::lock` or, without a lock, those of the last successful build.
+    pub fn read_lock(revision: &Revision) -> Result<Option<BuildManifest>, Error> {
+        match Self::read_from(&Self::lock_file_path(revision))? {
+            Some(manifest) => Ok(Some(manifest)),
+            None => Self::read(revision),
+        }
+    }
+
+    fn read_from(path: &Path) -> Result<Option<BuildManifest>, Error> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = std::fs::read_to_string(path)?;
+        // A manifest we can't parse is as good as none, it will be rewritten by the next build.
+        Ok(serde_json::from_str(&contents).ok())
+    }
+
+    /// The status of a build with this manifest, given the manifest of the last build
+    /// and whether its libraries still exist.
+    pub fn status(&self, previous: Option<&BuildManifest>, built: bool) -> BuildStatus {
+        let previous = match previous {
+            Some(previous) if built => previous,
+            _ => return BuildStatus::NotBuilt,
+        };
+
+        if previous.source_head != self.source_head || previous.submodules != self.submodules {
+            BuildStatus::SourceChanged
+        } else if previous.defines != self.defines
+            || previous.generator != self.generator
+            || previous.profile != self.profile
+            || previous.runtime_only != self.runtime_only
+            || previous.config_cmake != self.config_cmake
+            || !self.post_build_steps.is_subset(&previous.post_build_steps)
+        {
+            BuildStatus::SettingsChanged
+        } else {
+            BuildStatus::UpToDate
+        }
+    }
+
+    /// Whether this manifest drops any of the defines `previous` was built with.
+    ///
+    /// Changing or adding settings is compatible with the existing build tree, and the
+    /// generator only recompiles what the change affects, but CMake would keep the
+    /// cached value of a removed define so those need a fresh configure.
+    pub fn removes_defines(&self, previous: &BuildManifest) -> bool {
+        previous
+            .defines
+            .iter()
+            .any(|(key, _)| define_value(&self.defines, key).is_none())
+    }
+
+    /// Whether this manifest uses a different toolchain than `previous`, that is the
+    /// generator, compilers, toolchain file or the system and architecture being targeted.
+    ///
+    /// Nothing in the build tree can be reused when the toolchain changes.
+    pub fn changes_toolchain(&self, previous: &BuildManifest) -> bool {
+        previous.generator != self.generator
+            || TOOLCHAIN_DEFINES.iter().any(|key| {
+                define_value(&self.defines, key) != define_value(&previous.defines, key)
+            })
+    }
+
+    /// Record the generator the build is configured with.
+    pub fn with_generator(mut self, generator: String) -> BuildManifest {
+        self.generator = Some(generator);
+        self
+    }
+
+    /// Record the build type the build is configured with.
+    pub fn with_profile(mut self, profile: Profile) -> BuildManifest {
+        self.profile = Some(profile.to_string());
+        self
+    }
+
+    /// Record whether only the runtime library is built.
+    pub fn with_runtime_only(mut self, runtime_only: bool) -> BuildManifest {
+        self.runtime_only = runtime_only;
+        self
+    }
+
+    /// Record the digest of the `config.cmake` the build is configured with.
+    pub fn with_config_cmake(mut self, config_cmake: Option<String>) -> BuildManifest {
+        self.config_cmake = config_cmake;
+        self
+    }
+
+    /// Record the steps which run after the build.
+    pub fn with_post_build_steps(mut self, steps: BTreeSet<String>) -> BuildManifest {
+        self.post_build_steps = steps;
+        self
+    }
+
+    pub fn write(&self, revision: &Revision) -> Result<(), Error> {
+        self.write_to(revision, &Self::path(revision))
+    }
+
+    /// Write the manifest as the lock of `revision`, see `BuildManifest::read_lock`.
+    pub fn write_lock(&self, revision: &Revision) -> Result<(), Error> {
+        self.write_to(revision, &Self::lock_file_path(revision))
+    }
+
+    fn write_to(&self, revision: &Revision, path: &Path) -> Result<(), Error> {
+        let contents = serde_json::to_string_pretty(self).expect("manifests are always valid json");
+        std::fs::create_dir_all(revision.build_path())?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// The value `key` is defined to, the last definition wins as it does in CMake.
+fn define_value<'a>(defines: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    defines
+        .iter()
+        .rev()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(post_build_steps: &[&str]) -> BuildManifest {
+        BuildManifest {
+            source_head: "0123456789abcdef0123456789abcdef01234567".into(),
+            submodules: BTreeMap::new(),
+            defines: vec![("USE_LLVM".into(), "ON".into())],
+            generator: Some("Ninja".into()),
+            profile: None,
+            runtime_only: false,
+            config_cmake: None,
+            post_build_steps: post_build_steps.iter().map(|step| step.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn status_requires_the_requested_post_build_steps() {
+        let previous = manifest(&["cpp_tests", "strip"]);
+        assert_eq!(manifest(&[]).status(Some(&previous), true), BuildStatus::UpToDate);
+        assert_eq!(manifest(&["strip"]).status(Some(&previous), true), BuildStatus::UpToDate);
+        assert_eq!(
+            manifest(&["strip", "docs"]).status(Some(&previous), true),
+            BuildStatus::SettingsChanged
+        );
+        assert_eq!(manifest(&[]).status(Some(&previous), false), BuildStatus::NotBuilt);
+    }
+
+    #[test]
+    fn status_compares_config_cmake() {
+        let previous = manifest(&[]).with_config_cmake(Some("a".repeat(64)));
+        let edited = manifest(&[]).with_config_cmake(Some("b".repeat(64)));
+        assert_eq!(edited.status(Some(&previous), true), BuildStatus::SettingsChanged);
+        assert_eq!(manifest(&[]).status(Some(&previous), true), BuildStatus::SettingsChanged);
+        assert_eq!(previous.status(Some(&previous), true), BuildStatus::UpToDate);
+    }
+}
@@ -0,0 +1,106 @@
+//! Installing prebuilt releases of TVM instead of building them from source.
+//!
+//! Prebuilt releases are looked up relative to a base URL, for a release tag `v0.8.0`
+//! and target `x86_64-unknown-linux-gnu` we fetch
+//! `<base>/v0.8.0/tvm-v0.8.0-x86_64-unknown-linux-gnu.tar.gz`. Archives contain a
+//! revision directory, that is the `source` and `build` directories, and are extracted
+//! in place of a clone and build.
+//!
+//! Archives are always verified against a SHA-256 digest, either the one supplied by
+//! the caller or the one published next to the archive in `<archive>.sha256`.
+use std::{fs::File, io::Write};
+
+use tracing::info;
+
+use super::archive::{self, Format};
+use super::checksum;
+use super::core::{Error, Revision};
+use super::targets::Target;
+
+/// Whether a revision names a release tag, e.g. `v0.8.0`.
+fn is_release_tag(revision: &str) -> bool {
+    revision
+        .strip_prefix('v')
+        .map(|version| {
+            let parts: Vec<_> = version.split('.').collect();
+            parts.len() == 3
+                && parts
+                    .iter()
+                    .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        })
+        .unwrap_or(false)
+}
+
+/// Download `url` into `writer`, returning `false` if it doesn't exist.
+fn download<W: Write>(url: &str, writer: &mut W) -> Result<bool, Error> {
+    let failed = |message: String| Error::DownloadFailed {
+        url: url.to_string(),
+        message,
+    };
+
+    let response = match ureq::get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404, _)) => return Ok(false),
+        Err(err) => return Err(failed(err.to_string())),
+    };
+
+    std::io::copy(&mut response.into_reader(), writer).map_err(|err| failed(err.to_string()))?;
+    Ok(true)
+}
+
+/// Install the prebuilt release of `revision` for `target` if one exists, returning
+/// whether it did.
+///
+/// The archive is checked against `expected_sha256` when provided.
+pub(crate) fn install(
+    base_url: &str,
+    revision: &Revision,
+    target: &Target,
+    expected_sha256: Option<&str>,
+) -> Result<bool, Error> {
+    let tag = revision.name();
+    if !is_release_tag(tag) {
+        return Ok(false);
+    }
+
+    let archive_url = format!(
+        "{}/{}/tvm-{}-{}.tar.gz",
+        base_url.trim_end_matches('/'),
+        tag,
+        tag,
+        target.target_str
+    );
+
+    let expected = match expected_sha256 {
+        Some(expected) => expected.to_string(),
+        None => {
+            let mut checksum = vec![];
+            if !download(&format!("{}.sha256", archive_url), &mut checksum)? {
+                return Ok(false);
+            }
+            // The file is in `sha256sum` format, the digest followed by the file name.
+            String::from_utf8_lossy(&checksum)
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        }
+    };
+
+    let revision_path = revision.path();
+    std::fs::create_dir_all(&revision_path)?;
+    let archive_path = revision_path.with_extension("tar.gz.partial");
+
+    info!(url = %archive_url, "downloading prebuilt release");
+    if !download(&archive_url, &mut File::create(&archive_path)?)? {
+        std::fs::remove_file(&archive_path)?;
+        return Ok(false);
+    }
+
+    checksum::verify_file(&archive_path, &expected)?;
+    archive::extract(&archive_path, Format::TarGz, &revision_path)?;
+    std::fs::remove_file(&archive_path)?;
+
+    info!(path = %revision_path.display(), "installed prebuilt release");
+    Ok(true)
+}
@@ -0,0 +1,54 @@
+/// A structured description of where a build is at, for rendering progress bars
+/// and the like.
+///
+/// Events are delivered through `BuildConfig::progress`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildEvent {
+    /// Objects are being fetched while cloning the repository, `received_bytes` in all.
+    Cloning {
+        received_objects: usize,
+        total_objects: usize,
+        received_bytes: usize,
+    },
+    /// The `index`th of `total` submodules is being updated, counting from 1.
+    UpdatingSubmodule {
+        name: String,
+        index: usize,
+        total: usize,
+    },
+    /// Objects are being fetched for a submodule.
+    FetchingSubmodule {
+        name: String,
+        received_objects: usize,
+        total_objects: usize,
+    },
+    /// CMake is configuring the project.
+    Configuring,
+    /// The project is compiling, `completed` of `total` steps are done.
+    ///
+    /// Ninja reports the number of build steps while Make only reports a percentage,
+    /// in which case `total` is always 100.
+    Compiling { completed: usize, total: usize },
+    /// The build completed successfully.
+    Finished,
+}
+
+/// Parse a line of build tool output into a progress event.
+///
+/// Ninja prefixes its status lines with `[completed/total]` and Make with `[ NN%]`.
+pub(crate) fn parse_build_line(line: &str) -> Option<BuildEvent> {
+    let status = line.trim_start().strip_prefix('[')?;
+    let status = &status[..status.find(']')?];
+
+    if let Some((completed, total)) = status.split_once('/') {
+        Some(BuildEvent::Compiling {
+            completed: completed.trim().parse().ok()?,
+            total: total.trim().parse().ok()?,
+        })
+    } else {
+        Some(BuildEvent::Compiling {
+            completed: status.strip_suffix('%')?.trim().parse().ok()?,
+            total: 100,
+        })
+    }
+}
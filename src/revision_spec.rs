@@ -0,0 +1,68 @@
+//! A shorthand naming a revision and, optionally, the repository it lives in.
+use std::str::FromStr;
+
+use super::core::BuildConfig;
+
+/// The host a repository given as `owner/name` is fetched from.
+const GITHUB: &str = "https://github.com";
+
+/// A revision in a repository, written `v0.10.0` for the default repository or
+/// `apache/tvm@v0.10.0` for a repository on GitHub. A full URL may be given before
+/// the `@` as well, e.g. `https://example.com/tvm.git@main`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevisionSpec {
+    /// The repository URL, `None` for the default repository.
+    pub repository: Option<String>,
+    /// The branch, tag or commit.
+    pub revision: String,
+}
+
+impl RevisionSpec {
+    /// Set the repository and revision of `config` to the ones named.
+    pub fn apply(self, config: &mut BuildConfig) {
+        config.branch = Some(self.revision);
+        if self.repository.is_some() {
+            config.repository = self.repository;
+        }
+    }
+}
+
+/// Expand `owner/name` to its GitHub URL, anything else is taken to be a URL or path.
+fn repository_url(repository: &str) -> String {
+    let is_shorthand = repository.matches('/').count() == 1
+        && !repository.contains(':')
+        && !repository.starts_with('.')
+        && !repository.starts_with('/');
+    if is_shorthand {
+        format!("{}/{}", GITHUB, repository)
+    } else {
+        repository.to_string()
+    }
+}
+
+impl FromStr for RevisionSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Revisions can't contain `:`, so an `@` followed by one belongs to a URL such as
+        // `git@github.com:apache/tvm`.
+        match s.rsplit_once('@') {
+            Some((repository, revision)) if !revision.contains(':') => {
+                if repository.is_empty() || revision.is_empty() {
+                    return Err(format!(
+                        "`{}` should name a repository and revision as `owner/name@revision`",
+                        s
+                    ));
+                }
+                Ok(RevisionSpec {
+                    repository: Some(repository_url(repository)),
+                    revision: revision.to_string(),
+                })
+            }
+            _ => Ok(RevisionSpec {
+                repository: None,
+                revision: s.to_string(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Stripping the symbols from installed libraries, for smaller artifacts to ship.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tracing::{debug, info, warn};
+
+use super::core::Error;
+use super::detect;
+
+/// The size of a library before and after stripping it.
+#[derive(Debug, Clone)]
+pub struct StrippedLibrary {
+    pub path: PathBuf,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+/// Whether `path` is a shared library, the symbols of static libraries are needed to
+/// link against them.
+fn is_shared_library(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("so" | "dylib")
+    )
+}
+
+/// Strip the symbols not needed for dynamic linking from the shared libraries among
+/// `libraries`, with the tool named by `STRIP` or otherwise `strip`.
+///
+/// Stripping is an optimization, so when the tool is missing or fails we warn and
+/// leave the library as it is.
+pub(crate) fn strip_libraries(libraries: &[PathBuf]) -> Result<Vec<StrippedLibrary>, Error> {
+    let tool = std::env::var("STRIP").unwrap_or_else(|_| "strip".to_string());
+    let tool = match detect::find_executable(&tool) {
+        Some(tool) => tool,
+        None => {
+            warn!(%tool, "not stripping the libraries, the strip tool was not found");
+            return Ok(vec![]);
+        }
+    };
+
+    // Apple's strip removes everything by default, `-x` keeps the global symbols.
+    let flag = if cfg!(target_os = "macos") { "-x" } else { "--strip-unneeded" };
+
+    let mut stripped = vec![];
+    for path in libraries.iter().filter(|path| is_shared_library(path)) {
+        let size_before = std::fs::metadata(path)?.len();
+        let mut cmd = Command::new(&tool);
+        cmd.arg(flag).arg(path);
+        debug!(command = ?cmd, "running");
+        let output = cmd.output()?;
+        if !output.status.success() {
+            warn!(
+                library = %path.display(),
+                error = %String::from_utf8_lossy(&output.stderr).trim(),
+                "stripping the library failed, leaving it as is"
+            );
+            continue;
+        }
+
+        let size_after = std::fs::metadata(path)?.len();
+        info!(library = %path.display(), size_before, size_after, "stripped");
+        stripped.push(StrippedLibrary {
+            path: path.clone(),
+            size_before,
+            size_after,
+        });
+    }
+
+    Ok(stripped)
+}
@@ -2,14 +2,155 @@ mod target;
 
 pub use target::Target;
 
+use super::core::Error;
+use super::detect;
+
+/// Look up the compiler for a cross target, following the `cc` crate's convention of
+/// `CC_<triple>`/`CXX_<triple>` environment variables before falling back to `default`.
+fn cross_compiler(kind: &str, triple: &str, default: &str) -> Result<String, Error> {
+    let compiler = std::env::var(format!("{}_{}", kind, triple))
+        .or_else(|_| std::env::var(format!("{}_{}", kind, triple.replace('-', "_"))))
+        .unwrap_or_else(|_| default.to_string());
+
+    match detect::find_executable(&compiler) {
+        Some(path) => Ok(path.display().to_string()),
+        None => Err(Error::ToolchainNotFound {
+            target: triple.to_string(),
+            compiler,
+        }),
+    }
+}
+
+/// The ABIs of Linux triples we cross compile to, the last part of the triple.
+const LINUX_ENVIRONMENTS: &[&str] =
+    &["gnu", "gnueabi", "gnueabihf", "musl", "musleabi", "musleabihf"];
+
+/// The CMake processor name and GNU toolchain architecture of a triple's architecture,
+/// for example `armv7` toolchains are named `arm-linux-gnueabihf`.
+fn linux_architecture(architecture: &str) -> Option<(&'static str, &'static str)> {
+    Some(match architecture {
+        "aarch64" => ("aarch64", "aarch64"),
+        "armv7" => ("armv7", "arm"),
+        "arm" => ("arm", "arm"),
+        "x86_64" => ("x86_64", "x86_64"),
+        "i686" => ("i686", "i686"),
+        "riscv64gc" => ("riscv64", "riscv64"),
+        "powerpc64le" => ("ppc64le", "powerpc64le"),
+        "s390x" => ("s390x", "s390x"),
+        _ => return None,
+    })
+}
+
+/// A target for cross compiling TVM to a Linux system with a GNU toolchain prefixed
+/// by `toolchain_prefix`.
+fn cross_linux_target(triple: &str, processor: &str, toolchain_prefix: &str) -> Result<Target, Error> {
+    let cc = cross_compiler("CC", triple, &format!("{}-gcc", toolchain_prefix))?;
+    let cxx = cross_compiler("CXX", triple, &format!("{}-g++", toolchain_prefix))?;
+
+    Ok(Target {
+        host: "Linux".into(),
+        target_str: triple.into(),
+        cmake_defines: vec![
+            ("CMAKE_SYSTEM_NAME".into(), "Linux".into()),
+            ("CMAKE_SYSTEM_PROCESSOR".into(), processor.into()),
+            ("CMAKE_C_COMPILER".into(), cc),
+            ("CMAKE_CXX_COMPILER".into(), cxx),
+        ],
+    })
+}
+
+/// The defines selecting the architecture for Apple platforms.
+fn apple_defines(architecture: &str) -> Vec<(String, String)> {
+    vec![("CMAKE_OSX_ARCHITECTURES".into(), architecture.into())]
+}
+
+/// A target for iOS devices or, with `simulator`, the iOS simulator on Apple silicon.
+///
+/// The deployment target is read from `IPHONEOS_DEPLOYMENT_TARGET`, as Xcode does.
+fn ios_target(triple: &str, simulator: bool) -> Result<Target, Error> {
+    if !cfg!(target_os = "macos") {
+        return Err(Error::RequiresHost {
+            target: triple.to_string(),
+            host: "macOS".to_string(),
+        });
+    }
+
+    let deployment_target =
+        std::env::var("IPHONEOS_DEPLOYMENT_TARGET").unwrap_or_else(|_| "13.0".to_string());
+    let sdk = if simulator { "iphonesimulator" } else { "iphoneos" };
+
+    let mut cmake_defines = apple_defines("arm64");
+    cmake_defines.extend(vec![
+        ("CMAKE_SYSTEM_NAME".into(), "iOS".into()),
+        ("CMAKE_OSX_DEPLOYMENT_TARGET".into(), deployment_target),
+        ("CMAKE_OSX_SYSROOT".into(), sdk.into()),
+    ]);
+
+    Ok(Target {
+        host: "Darwin".into(),
+        target_str: triple.into(),
+        cmake_defines,
+    })
+}
+
+/// A target for macOS on `architecture`, which has to be built on macOS.
+fn macos_target(triple: &str, architecture: &str) -> Result<Target, Error> {
+    if !cfg!(target_os = "macos") {
+        return Err(Error::RequiresHost {
+            target: triple.to_string(),
+            host: "macOS".to_string(),
+        });
+    }
+
+    Ok(Target {
+        host: "Darwin".into(),
+        target_str: triple.into(),
+        cmake_defines: apple_defines(architecture),
+    })
+}
+
+/// Construct the target for a target triple, for cross compilation.
+///
+/// Linux triples of the form `<arch>-<vendor>-linux-<abi>`, such as
+/// `aarch64-unknown-linux-gnu` or `armv7-unknown-linux-gnueabihf`, build with the
+/// `<arch>-linux-<abi>-gcc` toolchain unless `CC_<triple>`/`CXX_<triple>` are set.
+/// The host's own triple builds for the host.
+pub fn target_from_triple(triple: &str) -> Result<Target, Error> {
+    let local = local_target();
+    if triple == local.target_str {
+        return Ok(local);
+    }
+
+    let unsupported = || Error::UnsupportedTarget(triple.to_string());
+    let mut parts = triple.splitn(3, '-');
+    let (architecture, _vendor, system) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(architecture), Some(vendor), Some(system)) => (architecture, vendor, system),
+        _ => return Err(unsupported()),
+    };
+
+    match (architecture, system) {
+        ("aarch64", "ios") => ios_target(triple, false),
+        ("aarch64", "ios-sim") => ios_target(triple, true),
+        ("aarch64", "darwin") => macos_target(triple, "arm64"),
+        ("x86_64", "darwin") => macos_target(triple, "x86_64"),
+        (architecture, system) => {
+            let environment = system.strip_prefix("linux-").ok_or_else(unsupported)?;
+            if !LINUX_ENVIRONMENTS.contains(&environment) {
+                return Err(unsupported());
+            }
+            let (processor, toolchain) = linux_architecture(architecture).ok_or_else(unsupported)?;
+            let toolchain_prefix = format!("{}-linux-{}", toolchain, environment);
+            cross_linux_target(triple, processor, &toolchain_prefix)
+        }
+    }
+}
+
 pub fn local_target() -> Target {
     let platform = futures::executor::block_on(heim::host::platform()).unwrap();
     match platform.system() {
         "Darwin" => {
             let cmake_defines = match platform.architecture() {
-                heim::host::Arch::Unknown | heim::host::Arch::AARCH64 => {
-                    vec![("CMAKE_OSX_ARCHITECTURES".into(), "arm64".into())]
-                }
+                heim::host::Arch::Unknown | heim::host::Arch::AARCH64 => apple_defines("arm64"),
                 _ => vec![],
             };
 
@@ -20,18 +161,33 @@ pub fn local_target() -> Target {
             }
         }
         "Linux" => {
-            let target = match platform.architecture() {
-                heim::host::Arch::AARCH64 => "aarch64-unknown-linux-gnu",
-                heim::host::Arch::X86_64 => "x86_64-unknown-linux-gnu",
+            let (target, cmake_defines) = match platform.architecture() {
+                heim::host::Arch::AARCH64 => (
+                    "aarch64-unknown-linux-gnu",
+                    vec![("CMAKE_SYSTEM_PROCESSOR".into(), "aarch64".into())],
+                ),
+                heim::host::Arch::X86_64 => ("x86_64-unknown-linux-gnu", vec![]),
                 _ => panic!("not supported"),
             };
 
             Target {
                 host: "Linux".into(),
                 target_str: target.into(),
-                cmake_defines: vec![],
+                cmake_defines,
             }
         }
+        // Windows builds with MSVC, see `detect::default_generator` for the generator.
+        "Windows" => Target {
+            host: "Windows".into(),
+            target_str: env!("TARGET").into(),
+            cmake_defines: vec![],
+        },
+        // The BSDs build with the same Unix Makefiles flow as Linux.
+        system @ ("FreeBSD" | "NetBSD" | "OpenBSD" | "DragonFly") => Target {
+            host: system.into(),
+            target_str: env!("TARGET").into(),
+            cmake_defines: vec![],
+        },
         _ => {
             panic!(
                 "Platform `{}` unsupported, please check the issue tracker.",
@@ -6,3 +6,10 @@ pub struct Target {
     pub target_str: String,
     pub cmake_defines: Vec<(String, String)>,
 }
+
+impl Target {
+    /// Whether the target is one of Apple's platforms.
+    pub fn is_apple(&self) -> bool {
+        self.target_str.contains("-apple-")
+    }
+}
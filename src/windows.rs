@@ -0,0 +1,15 @@
+//! The parts of a build which differ on Windows.
+//!
+//! The build itself goes through `Revision::build_for` as on other platforms: the
+//! Visual Studio generators are multi-config, so the profile is chosen with `--config`
+//! when building rather than `CMAKE_BUILD_TYPE`, which `cmake::Config` passes for every
+//! generator. What differs is where the libraries end up, a DLL is a runtime file
+//! which CMake installs to `bin` unless told otherwise, while its import library, the
+//! `.lib` linked against, is installed to `lib`.
+use std::path::{Path, PathBuf};
+
+/// The directories below the install prefix `build_path` the TVM libraries are
+/// installed to, the import libraries first.
+pub(crate) fn library_dirs(build_path: &Path) -> Vec<PathBuf> {
+    vec![build_path.join("lib"), build_path.join("bin")]
+}